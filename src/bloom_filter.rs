@@ -0,0 +1,2807 @@
+use std::hash::{Hash, Hasher, BuildHasher};
+use std::borrow::Cow;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashSet;
+use std::mem;
+
+use crate::{BloomFilterBuilder, BloomFilterError, BloomHash, IndexStrategy};
+#[cfg(feature = "hll")]
+use crate::hyperloglog::HyperLogLog;
+
+mod cascade;
+mod deletable;
+mod serialization;
+
+pub use cascade::FilterCascade;
+pub use deletable::DeletableBloomFilter;
+
+
+/// Generate a random seed using RandomState
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Derive a seed from key bytes with a fixed-key SipHash, so equal keys always give equal seeds
+fn key_seed(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(b"bloom_filter_rs key");
+    hasher.write(key);
+    hasher.finish()
+}
+
+/// Derive the two base hashers used for double hashing from a single seed
+fn seeded_hashers(seed: u64) -> [DefaultHasher; 2] {
+    [0u64, 1].map(|i| {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(seed);
+        hasher.write_u64(i);
+        hasher
+    })
+}
+
+/// Largest bit vector length whose words can still be counted and allocated
+const MAX_VECTOR_LEN: usize = usize::MAX / 64;
+
+/// Most hashers `for_memory_budget` picks: past it the false positive rate is already negligible,
+/// and every extra hasher costs each add and lookup another probe
+const MAX_BUDGET_HASHERS: usize = 64;
+
+/// Largest bit vector length whose positions all fit in the u32s of the sparse form
+const MAX_SPARSE_VECTOR_LEN: u64 = 1 << 32;
+
+/// Seed the salt of `new_with_salt` is XORed into
+const SALT_BASE_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Default relative excess over prob_fp tolerated by the strict-debug check
+pub(crate) const DEFAULT_FP_TOLERANCE: f64 = 1.0;
+
+/// Smaller filters are skipped by the strict-debug check, their fill varies too much between seeds
+#[cfg(feature = "strict-debug")]
+const MIN_CHECKED_VECTOR_LEN: usize = 1024;
+
+/// Bloom filter is a space-efficient probabilistic data structure. \
+/// Refer <https://en.wikipedia.org/wiki/Bloom_filter>
+///
+/// `BloomFilter` is `Send + Sync`: it can be moved across threads and shared for concurrent
+/// lookups (e.g. behind an `Arc`), while `add` still needs exclusive access.
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct BloomFilter {
+    prob_fp: f64,
+    data_set_size: usize,
+    vector_len: usize,   // optimal vector len computed  
+    num_hashers: usize,   // optimal number of hasers
+    
+    bitvec: Vec<u64>,      // packed bits, bit i lives in word i / 64 at position i % 64
+    seed: u64,             // both base hashers are derived from this
+    hash_funcs: [DefaultHasher; 2],   // SipHasher is deprecated
+    index_strategy: IndexStrategy,
+
+    inserted: usize,       // number of items added so far
+    strict: bool,          // reject inserts beyond data_set_size
+    fp_tolerance: f64,     // allowed relative excess over prob_fp, checked with the strict-debug feature
+    reject_empty: bool,    // empty keys are never inserted and never present
+    branchless: bool,      // lookups test all num_hashers bits instead of stopping at the first clear one
+
+    #[cfg(feature = "hll")]
+    hyperloglog: HyperLogLog   // distinct count of the items added, from their first base hash
+}
+
+/// Everything that defines a filter except its bits, from [`BloomFilter::into_parts`]. \
+/// Small and made of plain fields, so it can be stored apart from the bit words, e.g. in a database.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterConfig {
+    /// Target false positive probability
+    pub prob_fp: f64,
+    /// Number of items the filter was sized for
+    pub data_set_size: usize,
+    /// Length of the bit vector
+    pub vector_len: usize,
+    /// Number of bit indexes set per item
+    pub num_hashers: usize,
+    /// Seed both base hashers are derived from
+    pub seed: u64,
+    /// How hashes are mapped onto bit indexes
+    pub index_strategy: IndexStrategy,
+    /// Number of items added so far
+    pub inserted: usize,
+    /// Whether inserts beyond data_set_size are rejected
+    pub strict: bool,
+    /// Allowed relative excess over prob_fp, checked with the strict-debug feature
+    pub fp_tolerance: f64,
+    /// Whether empty keys are treated as never present
+    pub reject_empty: bool,
+    /// Whether lookups test every bit without short-circuiting
+    pub branchless: bool,
+}
+
+/// In-process checkpoint of a filter's bits and configuration, from [`BloomFilter::snapshot`]
+/// and put back with [`BloomFilter::restore`]
+#[derive(Clone, Debug)]
+pub struct FilterSnapshot {
+    bloom_filter: BloomFilter,
+}
+
+/// Outcome of [`BloomFilter::merge_items`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MergeReport {
+    /// Number of items added from the batch
+    pub inserted_count: usize,
+    /// False positive rate after the batch, see [`BloomFilter::current_false_positive_rate`]
+    pub current_fp_rate: f64,
+    /// Whether the batch pushed the filter past its target rate, see [`BloomFilter::needs_rebuild`]
+    pub needs_rebuild: bool,
+}
+
+
+impl BloomFilter {
+
+    /// Create new bloom filter given  \
+    /// prob_fp : Max Tolerable Probability of False Positive  \
+    /// data_set_size : Estimated Max Set Size
+    ///
+    /// The computed dimensions are clamped so that the filter always has at least
+    /// one bit and at least one hasher, even for tiny inputs like `data_set_size = 1`.
+    pub fn new(prob_fp: f64, data_set_size: usize) -> Self {
+
+        Self::with_seed(prob_fp, data_set_size, random_seed())
+    }
+
+    /// Same as `new`, but validates the parameters first: `prob_fp` must be a finite number in (0, 1)
+    /// and `data_set_size` non-zero. `new` takes them as is, so NaN or infinite `prob_fp` give
+    /// a nonsensical filter. \
+    /// The bits are allocated fallibly too: where `new` aborts the process when memory runs out,
+    /// this returns `AllocationFailed`.
+    pub fn try_new(prob_fp: f64, data_set_size: usize) -> Result<Self, BloomFilterError> {
+        let (vector_len, num_hashers) = Self::optimal_params(prob_fp, data_set_size)?;
+
+        let bitvec = Self::try_zeroed_words(Self::words_for(vector_len))?;
+        Ok(Self::with_bits(prob_fp, data_set_size, vector_len, num_hashers, random_seed(), bitvec))
+    }
+
+    /// Clear bit words allocated fallibly, `AllocationFailed` when memory runs out
+    fn try_zeroed_words(words: usize) -> Result<Vec<u64>, BloomFilterError> {
+        let mut bitvec = Vec::new();
+        bitvec.try_reserve_exact(words).map_err(|_| BloomFilterError::AllocationFailed(words.saturating_mul(mem::size_of::<u64>())))?;
+        bitvec.resize(words, 0);
+        Ok(bitvec)
+    }
+
+    /// Snapshot of an existing set: a filter sized for `set.len()` items at prob_fp, holding every element. \
+    /// Fails like `try_new`, in particular for an empty set.
+    pub fn from_hashset<T: Hash, S>(set: &HashSet<T, S>, prob_fp: f64) -> Result<BloomFilter, BloomFilterError> {
+        let mut bloom_filter = Self::try_new(prob_fp, set.len())?;
+        for item in set {
+            bloom_filter.add(item);
+        }
+        Ok(bloom_filter)
+    }
+
+    /// Same as `new`, but the hasher seed is derived deterministically from a secret key,
+    /// so adversaries who don't know the key can't precompute inputs that collide in the filter. \
+    /// Filters built with the same key and parameters set the same bits for the same items.
+    pub fn new_with_key(prob_fp: f64, data_set_size: usize, key: &[u8]) -> Self {
+
+        Self::with_seed(prob_fp, data_set_size, key_seed(key))
+    }
+
+    /// Same as `new`, but with a per-replica salt mixed into the base hashers instead of a random
+    /// seed. Replicas with distinct salts map the same item to different bits, so their false
+    /// positives are independent and voting over them (see `majority_contains`) pays off; replicas
+    /// sharing a salt stay compatible.
+    pub fn new_with_salt(prob_fp: f64, data_set_size: usize, salt: u64) -> Self {
+
+        Self::with_seed(prob_fp, data_set_size, SALT_BASE_SEED ^ salt)
+    }
+
+    /// Reassembles a filter from its raw parts: the packed bit words (bit i in word i / 64 at
+    /// position i % 64) and the parameters they were built with. \
+    /// The parts must pass `validate`; extra trailing words are allowed as long as they are clear,
+    /// and `compact` drops them.
+    pub fn from_raw_parts(prob_fp: f64, data_set_size: usize, vector_len: usize, num_hashers: usize, seed: u64, bits: Vec<u64>) -> Result<Self, BloomFilterError> {
+        let bloom_filter = Self::with_bits(prob_fp, data_set_size, vector_len, num_hashers, seed, bits);
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Splits the filter into its configuration and its packed bit words, for storing them
+    /// separately. `from_parts` puts them back together.
+    pub fn into_parts(self) -> (FilterConfig, Vec<u64>) {
+        (self.config(), self.bitvec)
+    }
+
+    fn config(&self) -> FilterConfig {
+        FilterConfig {
+            prob_fp: self.prob_fp,
+            data_set_size: self.data_set_size,
+            vector_len: self.vector_len,
+            num_hashers: self.num_hashers,
+            seed: self.seed,
+            index_strategy: self.index_strategy,
+            inserted: self.inserted,
+            strict: self.strict,
+            fp_tolerance: self.fp_tolerance,
+            reject_empty: self.reject_empty,
+            branchless: self.branchless,
+        }
+    }
+
+    /// Reassembles a filter from `into_parts`, checking the result with `validate`
+    pub fn from_parts(config: FilterConfig, bits: Vec<u64>) -> Result<BloomFilter, BloomFilterError> {
+        let mut bloom_filter = Self::with_bits(config.prob_fp, config.data_set_size, config.vector_len, config.num_hashers, config.seed, bits);
+        bloom_filter.index_strategy = config.index_strategy;
+        bloom_filter.inserted = config.inserted;
+        bloom_filter.strict = config.strict;
+        bloom_filter.fp_tolerance = config.fp_tolerance;
+        bloom_filter.reject_empty = config.reject_empty;
+        bloom_filter.branchless = config.branchless;
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Sorted indexes of the set bits, a compact wire form for lightly filled filters: 4 bytes per
+    /// set bit instead of one bit per position. `from_sparse` rebuilds the filter. \
+    /// Filters over 2^32 bits, whose positions don't all fit in a u32, fail with `FilterTooLarge`.
+    pub fn into_sparse(self) -> Result<Vec<u32>, BloomFilterError> {
+        if self.vector_len as u64 > MAX_SPARSE_VECTOR_LEN {
+            return Err(BloomFilterError::FilterTooLarge(self.data_set_size));
+        }
+
+        let mut positions = Vec::new();
+        for (word_index, &word) in self.bitvec.iter().enumerate() {
+            let mut remaining = word;
+            while remaining != 0 {
+                positions.push((word_index * 64) as u32 + remaining.trailing_zeros());
+                remaining &= remaining - 1;
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Rebuilds a filter from the set bit positions of `into_sparse` and the dimensions, seed and
+    /// index strategy it was built with. \
+    /// The sparse form doesn't carry prob_fp and data_set_size, so the filter gets the capacity of
+    /// its dimensions, `vector_len * ln 2 / num_hashers` items, and the false positive rate at that
+    /// capacity; `inserted` is set to `estimate_cardinality()`, at most the number of positions. \
+    /// The arguments usually come off the wire, so they are checked before trusting them: a
+    /// vector_len over 2^32, which `into_sparse` never produces, fails with `FilterTooLarge`,
+    /// storage that can't be allocated with `AllocationFailed`, and positions past vector_len
+    /// with `InvalidFormat`.
+    pub fn from_sparse(
+        vector_len: usize,
+        num_hashers: usize,
+        seed: u64,
+        index_strategy: IndexStrategy,
+        positions: &[u32],
+    ) -> Result<BloomFilter, BloomFilterError> {
+        if vector_len == 0 || num_hashers == 0 {
+            return Err(BloomFilterError::InvalidDimensions { vector_len, num_hashers });
+        }
+
+        let bits_per_item = num_hashers as f64 / f64::ln(2.0);
+        let data_set_size = (f64::round(vector_len as f64 / bits_per_item) as usize).max(1);
+        if vector_len as u64 > MAX_SPARSE_VECTOR_LEN {
+            return Err(BloomFilterError::FilterTooLarge(data_set_size));
+        }
+        let fill = 1.0 - f64::exp(-(num_hashers as f64 * data_set_size as f64) / vector_len as f64);
+        let prob_fp = fill.powf(num_hashers as f64);
+
+        let bitvec = Self::try_zeroed_words(Self::words_for(vector_len))?;
+        let mut bloom_filter = Self::with_bits(prob_fp, data_set_size, vector_len, num_hashers, seed, bitvec);
+        bloom_filter.index_strategy = index_strategy;
+        for &position in positions {
+            if position as usize >= vector_len {
+                return Err(BloomFilterError::InvalidFormat(format!("sparse position {} past vector_len {}", position, vector_len)));
+            }
+            bloom_filter.set_bit(position as usize);
+        }
+        // A saturated filter estimates usize::MAX items, which no later add could count past
+        bloom_filter.inserted = bloom_filter.estimate_cardinality().min(positions.len());
+        Ok(bloom_filter)
+    }
+
+    /// Checkpoint of the current state, e.g. to survive a failed batch during a long ingest. \
+    /// A plain copy of the bit words and settings, much cheaper than serializing.
+    pub fn snapshot(&self) -> FilterSnapshot {
+        FilterSnapshot { bloom_filter: self.clone() }
+    }
+
+    /// Rolls the filter back to a snapshot of itself or of a compatible filter, dropping everything
+    /// added since. Fails with `IncompatibleFilters` and leaves the filter untouched otherwise.
+    pub fn restore(&mut self, snapshot: FilterSnapshot) -> Result<(), BloomFilterError> {
+        self.check_compatible(&snapshot.bloom_filter)?;
+        *self = snapshot.bloom_filter;
+        Ok(())
+    }
+
+    /// Checks the internal invariants a filter assembled from external data could break:
+    /// - at least one bit and one hasher, otherwise `InvalidDimensions`
+    /// - storage for all `vector_len` bits, otherwise `IndexOutOfRange` for the last bit
+    /// - no bits set in storage past `vector_len`, otherwise `InvalidFormat`
+    pub fn validate(&self) -> Result<(), BloomFilterError> {
+        if self.vector_len == 0 || self.num_hashers == 0 {
+            return Err(BloomFilterError::InvalidDimensions { vector_len: self.vector_len, num_hashers: self.num_hashers });
+        }
+
+        let storage_bits = self.bitvec.len() * 64;
+        if storage_bits < self.vector_len {
+            return Err(BloomFilterError::IndexOutOfRange { index: self.vector_len - 1, storage_bits });
+        }
+
+        let boundary_word = self.vector_len / 64;   // first word holding bits past vector_len
+        let past_end_set = self.bitvec.get(boundary_word).is_some_and(|word| word >> (self.vector_len % 64) != 0)
+            || self.bitvec.iter().skip(boundary_word + 1).any(|&word| word != 0);
+        if past_end_set {
+            return Err(BloomFilterError::InvalidFormat(String::from("bits set past vector_len")));
+        }
+        Ok(())
+    }
+
+    /// Best filter for data_set_size items whose bits fit in max_bytes: it uses every whole u64 word
+    /// of the budget, picks the optimal `num_hashers = (vector_len / data_set_size) * ln 2`, at most 64,
+    /// and records the false positive rate achievable at capacity, `(1 - e^(-k * n / m))^k`, as its
+    /// `prob_fp`, kept inside (0, 1) even when it underflows for huge budgets per item. \
+    /// A budget too large to count in bits fails with `FilterTooLarge`, one that can't be allocated
+    /// with `AllocationFailed`.
+    pub fn for_memory_budget(data_set_size: usize, max_bytes: usize) -> Result<BloomFilter, BloomFilterError> {
+        if data_set_size == 0 {
+            return Err(BloomFilterError::InvalidDataSetSize);
+        }
+        let vector_len = (max_bytes / 8).checked_mul(64).ok_or(BloomFilterError::FilterTooLarge(data_set_size))?;
+        if vector_len == 0 {
+            return Err(BloomFilterError::MemoryBudgetTooSmall(max_bytes));
+        }
+
+        let bits_per_item = vector_len as f64 / data_set_size as f64;
+        let num_hashers = (f64::round(bits_per_item * f64::ln(2.0)) as usize).clamp(1, MAX_BUDGET_HASHERS);
+        let prob_fp = (-f64::exp_m1(-(num_hashers as f64) / bits_per_item))
+            .powi(num_hashers as i32)
+            .clamp(f64::MIN_POSITIVE, 1.0 - f64::EPSILON);
+
+        let bitvec = Self::try_zeroed_words(Self::words_for(vector_len))?;
+        Ok(Self::with_bits(prob_fp, data_set_size, vector_len, num_hashers, random_seed(), bitvec))
+    }
+
+    fn with_seed(prob_fp: f64, data_set_size: usize, seed: u64) -> Self {
+
+        let optimal_vector_len = Self::get_optimal_vector_len(prob_fp, data_set_size);
+        let optimal_num_hashes = Self::get_optimal_num_hashes(prob_fp);
+
+        Self::with_dimensions(prob_fp, data_set_size, optimal_vector_len, optimal_num_hashes, seed)
+    }
+
+    fn with_dimensions(prob_fp: f64, data_set_size: usize, vector_len: usize, num_hashers: usize, seed: u64) -> Self {
+
+        Self::with_bits(prob_fp, data_set_size, vector_len, num_hashers, seed, vec![0; Self::words_for(vector_len)])
+    }
+
+    fn with_bits(prob_fp: f64, data_set_size: usize, vector_len: usize, num_hashers: usize, seed: u64, bitvec: Vec<u64>) -> Self {
+
+        BloomFilter {
+            prob_fp,
+            data_set_size,
+            vector_len,
+            num_hashers,
+
+            bitvec,
+            seed,
+            hash_funcs: seeded_hashers(seed),
+            index_strategy: IndexStrategy::default(),
+
+            inserted: 0,
+            strict: false,
+            fp_tolerance: DEFAULT_FP_TOLERANCE,
+            reject_empty: false,
+            branchless: false,
+
+            #[cfg(feature = "hll")]
+            hyperloglog: HyperLogLog::new()
+        }
+    }
+
+    /// Start configuring a filter with non-default options, see [`BloomFilterBuilder`]
+    pub fn builder(prob_fp: f64, data_set_size: usize) -> BloomFilterBuilder {
+        BloomFilterBuilder::new(prob_fp, data_set_size)
+    }
+
+    pub(crate) fn from_builder(builder: &BloomFilterBuilder) -> Result<Self, BloomFilterError> {
+        let mut bloom_filter = Self::try_new(builder.prob_fp, builder.data_set_size)?;
+        bloom_filter.strict = builder.strict;
+        bloom_filter.index_strategy = builder.index_strategy;
+        bloom_filter.fp_tolerance = builder.fp_tolerance;
+        bloom_filter.reject_empty = builder.reject_empty;
+        bloom_filter.branchless = builder.branchless;
+        Ok(bloom_filter)
+    }
+
+    /// Allows addition of data of any type that implements Hash trait, or [`BloomHash`] for
+    /// key types hashed by hand
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` items were added. Use [`BloomFilter::try_add`] to get an error instead.
+    pub fn add<T: BloomHash>(&mut self, data: T) {
+
+        if let Err(err) = self.try_add(data) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Adds data by reference, leaving ownership with the caller. \
+    /// Equivalent to `add(data)` since `&T` hashes like `T`, but spelled out for generic code
+    /// and for `BloomHash` types, whose references don't implement it.
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` items were added.
+    pub fn add_ref<T: BloomHash + ?Sized>(&mut self, data: &T) {
+        if !self.rejects(data) {
+            self.add_hashes(self.hashes_of(data));
+        }
+    }
+
+    /// Same as `add`, but in strict mode returns `CapacityExceeded` instead of inserting
+    /// past `data_set_size`. \
+    /// Returns whether any bit was newly set, i.e. whether data was definitely not present before.
+    pub fn try_add<T: BloomHash>(&mut self, data: T) -> Result<bool, BloomFilterError> {
+
+        if self.rejects(&data) {
+            return Ok(false);
+        }
+        let hashes = self.compute_hashes(data);
+        self.insert_hashes(hashes)
+    }
+
+    /// Adds the raw content of a byte slice. \
+    /// Unlike `add(bytes)`, no length prefix is hashed, so only `contains_bytes` finds it.
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` items were added. Use [`BloomFilter::try_add_bytes`] to get an error instead.
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        if let Err(err) = self.try_add_bytes(bytes) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Same as `add_bytes`, but in strict mode returns `CapacityExceeded` instead of inserting
+    /// past `data_set_size`. \
+    /// Returns whether any bit was newly set, like `try_add`.
+    pub fn try_add_bytes(&mut self, bytes: &[u8]) -> Result<bool, BloomFilterError> {
+        if self.reject_empty && bytes.is_empty() {
+            return Ok(false);
+        }
+        let hashes = self.byte_hashes(bytes);
+        self.insert_hashes(hashes)
+    }
+
+    /// Adds the raw content of each slice like `add_bytes`. \
+    /// The batch is all or nothing in strict mode: its size is checked against the capacity left
+    /// before any slice is added. Empty slices dropped by `reject_empty` don't count.
+    ///
+    /// # Panics
+    /// In strict mode, if the batch doesn't fit in the `data_set_size` items left.
+    pub fn add_byte_slices<'a, I: IntoIterator<Item = &'a [u8]>>(&mut self, slices: I) {
+        let slices: Vec<&[u8]> = slices.into_iter().filter(|bytes| !(self.reject_empty && bytes.is_empty())).collect();
+        if let Err(err) = self.check_batch_capacity(slices.len()) {
+            panic!("{}", err);
+        }
+        for bytes in slices {
+            self.add_bytes(bytes);
+        }
+    }
+
+    /// Adds the bytes of an owned or borrowed key exactly like `add_bytes`, so callers that only
+    /// sometimes own their keys need a single code path
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` items were added.
+    pub fn add_cow(&mut self, key: Cow<[u8]>) {
+        self.add_bytes(&key);
+    }
+
+    /// Adds every non-empty prefix of key, `key[..1]`, `key[..2]`, ... up to the whole key, like
+    /// `add_bytes`, so that `contains_prefix` can answer "is there a key starting with this". \
+    /// Each prefix takes up a slot of `data_set_size`, so size the filter for the total prefix count.
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` prefixes were added.
+    pub fn add_all_prefixes<B: AsRef<[u8]>>(&mut self, key: B) {
+        let key = key.as_ref();
+        for end in 1..=key.len() {
+            self.add_bytes(&key[..end]);
+        }
+    }
+
+    /// Adds a batch of items to an existing filter and reports where that leaves it, so callers
+    /// can tell when it is time for `resize_rebuild`. \
+    /// All or nothing in strict mode: a batch that doesn't fit in the capacity left fails with
+    /// `CapacityExceeded` before any of its items is added. Empty keys dropped by `reject_empty`
+    /// take no capacity and aren't counted in `inserted_count`.
+    pub fn merge_items<T: BloomHash, I: IntoIterator<Item = T>>(&mut self, items: I) -> Result<MergeReport, BloomFilterError> {
+        let items: Vec<T> = items.into_iter().filter(|item| !self.rejects(item)).collect();
+        self.check_batch_capacity(items.len())?;
+
+        let inserted_count = items.len();
+        for item in items {
+            self.try_add(item)?;
+        }
+        Ok(MergeReport {
+            inserted_count,
+            current_fp_rate: self.current_false_positive_rate(),
+            needs_rebuild: self.needs_rebuild(),
+        })
+    }
+
+    /// Adds an item given its base hashes from `compute_hashes`. \
+    /// Hashes don't tell whether the key was empty, so `reject_empty` has to be applied before hashing.
+    ///
+    /// # Panics
+    /// In strict mode, once `data_set_size` items were added.
+    pub fn add_hashes(&mut self, hashes: (u64, u64)) {
+        if let Err(err) = self.insert_hashes(hashes) {
+            panic!("{}", err);
+        }
+    }
+
+    /// In strict mode, `CapacityExceeded` unless `count` more items fit in `data_set_size`
+    fn check_batch_capacity(&self, count: usize) -> Result<(), BloomFilterError> {
+        if self.strict && self.inserted.saturating_add(count) > self.data_set_size {
+            return Err(BloomFilterError::CapacityExceeded(self.data_set_size));
+        }
+        Ok(())
+    }
+
+    fn insert_hashes(&mut self, (hash_1, hash_2): (u64, u64)) -> Result<bool, BloomFilterError> {
+
+        if self.strict && self.inserted >= self.data_set_size {
+            return Err(BloomFilterError::CapacityExceeded(self.data_set_size));
+        }
+
+        // Indexes stay below vector_len, so they only overflow storage that is shorter than
+        // vector_len, i.e. a filter assembled from inconsistent raw parts. Check before setting any bit.
+        let storage_bits = self.bitvec.len() * 64;
+        if self.vector_len > storage_bits {
+            if let Some(index) = self.indexes_from_hashes(hash_1, hash_2).find(|&index| index >= storage_bits) {
+                return Err(BloomFilterError::IndexOutOfRange { index, storage_bits });
+            }
+        }
+
+        let mut newly_set = false;
+        for index in self.indexes_from_hashes(hash_1, hash_2) {
+            // println!("add {}", index);
+            newly_set |= self.set_bit(index);
+        }
+        self.inserted = self.inserted.saturating_add(1);
+
+        #[cfg(feature = "hll")]
+        self.hyperloglog.insert(hash_1);
+
+        #[cfg(feature = "strict-debug")]
+        if self.inserted == self.data_set_size && self.vector_len >= MIN_CHECKED_VECTOR_LEN {
+            let false_positive_rate = self.current_false_positive_rate();
+            debug_assert!(
+                false_positive_rate <= self.prob_fp * (1.0 + self.fp_tolerance),
+                "filter loaded to data_set_size = {} has false positive rate {} above target {} (tolerance {})",
+                self.data_set_size, false_positive_rate, self.prob_fp, self.fp_tolerance
+            );
+        }
+        Ok(newly_set)
+    }
+ 
+    /// Checks whether data is present or not \
+    /// - if False, data is not present with 100% probability \
+    /// - if True, data might or might not be present (Can be a false postiive)
+    #[must_use]
+    pub fn contains<T: BloomHash>(&self, data: T) -> bool {
+
+        !self.rejects(&data) && self.contains_hashes(self.compute_hashes(data))
+    }
+
+    /// `contains` for every item of a slice. \
+    /// Hashes the whole batch first and then tests bits, keeping each loop tight.
+    #[must_use]
+    pub fn contains_batch<T: BloomHash>(&self, items: &[T]) -> Vec<bool> {
+        let hashes: Vec<Option<(u64, u64)>> = items.iter().map(|item| (!self.rejects(item)).then(|| self.hashes_of(item))).collect();
+        hashes.into_iter().map(|hashes| hashes.is_some_and(|hashes| self.contains_hashes(hashes))).collect()
+    }
+
+    /// Fraction of the streamed items that `contains` reports as present, without keeping the
+    /// individual answers. False positives push it above the true fraction by up to about
+    /// `current_false_positive_rate()`. Returns 0.0 for no items.
+    #[must_use]
+    pub fn approx_present_fraction<T: BloomHash, I: IntoIterator<Item = T>>(&self, items: I) -> f64 {
+        let (hits, total) = items
+            .into_iter()
+            .fold((0usize, 0usize), |(hits, total), item| (hits + self.contains(item) as usize, total + 1));
+        if total == 0 {
+            return 0.0;
+        }
+        hits as f64 / total as f64
+    }
+
+    /// The candidates `contains` reports as present, in order. Debugging and testing aid: when
+    /// the caller knows none of the candidates was added, these are concrete false positives.
+    #[must_use]
+    pub fn find_false_positives<T: BloomHash, I: IntoIterator<Item = T>>(&self, candidates: I) -> Vec<T> {
+        candidates.into_iter().filter(|candidate| self.contains_ref(candidate)).collect()
+    }
+
+    /// Same as `contains`, named after what a true answer actually means: data was probably added,
+    /// but it can be a false positive
+    #[must_use]
+    pub fn is_probably_present<T: BloomHash>(&self, data: T) -> bool {
+        self.contains(data)
+    }
+
+    /// Negation of `contains`. A true answer is certain: data was never added.
+    #[must_use]
+    pub fn is_definitely_absent<T: BloomHash>(&self, data: T) -> bool {
+        !self.contains(data)
+    }
+
+    /// Checks data by reference, the lookup counterpart of `add_ref`
+    #[must_use]
+    pub fn contains_ref<T: BloomHash + ?Sized>(&self, data: &T) -> bool {
+        !self.rejects(data) && self.contains_hashes(self.hashes_of(data))
+    }
+
+    /// Exact membership with the filter in front of an authoritative store: items the filter rules
+    /// out are absent without consulting the store, and only possible positives are resolved by
+    /// calling `authoritative`, which should look data up in the store.
+    #[must_use]
+    pub fn contains_exact<T: BloomHash + ?Sized, F: FnMut(&T) -> bool>(&self, data: &T, mut authoritative: F) -> bool {
+        self.contains_ref(data) && authoritative(data)
+    }
+
+    /// Same as `contains`, plus the estimated probability that the answer is right. \
+    /// A present answer is a true positive with probability `1 - current_false_positive_rate()`;
+    /// an absent answer is always right, so its confidence is 1.0.
+    #[must_use]
+    pub fn contains_with_confidence<T: BloomHash>(&self, data: T) -> (bool, f64) {
+        if self.contains(data) {
+            (true, 1.0 - self.current_false_positive_rate())
+        } else {
+            (false, 1.0)
+        }
+    }
+
+    /// Checks whether the raw content of a byte slice was added with `add_bytes`
+    #[must_use]
+    pub fn contains_bytes(&self, bytes: &[u8]) -> bool {
+        !(self.reject_empty && bytes.is_empty()) && self.contains_hashes(self.byte_hashes(bytes))
+    }
+
+    /// Checks the bytes of an owned or borrowed key exactly like `contains_bytes`
+    #[allow(clippy::ptr_arg)]   // takes the Cow callers already hold, to pair with add_cow
+    #[must_use]
+    pub fn contains_cow(&self, key: &Cow<[u8]>) -> bool {
+        self.contains_bytes(key)
+    }
+
+    /// Checks whether some key added with `add_all_prefixes` (probably) starts with prefix
+    #[must_use]
+    pub fn contains_prefix<B: AsRef<[u8]>>(&self, prefix: B) -> bool {
+        self.contains_bytes(prefix.as_ref())
+    }
+
+    /// Checks whether an item is present given its base hashes from `compute_hashes`. \
+    /// Like `add_hashes`, leaves `reject_empty` to the caller.
+    #[must_use]
+    pub fn contains_hashes(&self, (hash_1, hash_2): (u64, u64)) -> bool {
+        if self.branchless {
+            return self.contains_branchless(hash_1, hash_2);
+        }
+        self.indexes_from_hashes(hash_1, hash_2).all(|index| self.get_bit(index))
+    }
+
+    /// Lookup ANDing all num_hashers bit tests together instead of returning at the first clear
+    /// bit. In dense filters most lookups test every bit anyway, and a loop without an early exit
+    /// avoids the mispredicted branch.
+    fn contains_branchless(&self, hash_1: u64, hash_2: u64) -> bool {
+        self.indexes_from_hashes(hash_1, hash_2).fold(true, |hits, index| hits & self.get_bit(index))
+    }
+
+    /// Length of the bit vector
+    pub fn vector_len(&self) -> usize {
+        self.vector_len
+    }
+
+    /// Number of bit indexes set per item
+    pub fn num_hashers(&self) -> usize {
+        self.num_hashers
+    }
+
+    /// Seed both base hashers are derived from. `new` draws it once at random and the filter keeps
+    /// it from then on, so clones, serialized copies and `resize_rebuild` share the bit geometry.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of items added so far, counting repeated additions
+    pub fn inserted(&self) -> usize {
+        self.inserted
+    }
+
+    /// Number of bits set in the bit vector
+    pub fn count_set_bits(&self) -> usize {
+        self.bitvec.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Fraction of bits set, between 0.0 and 1.0
+    pub fn fill_ratio(&self) -> f64 {
+        self.count_set_bits() as f64 / self.vector_len as f64
+    }
+
+    /// Shannon entropy of the set/clear bit distribution in bits, `-(p log2 p + (1 - p) log2 (1 - p))`
+    /// for the fill ratio `p`. Peaks at 1.0 for a half-full filter, which is where a filter at
+    /// capacity sits; a low value at high item counts suggests clustering.
+    pub fn bit_entropy(&self) -> f64 {
+        let p = self.fill_ratio();
+        if p == 0.0 || p == 1.0 {
+            return 0.0;
+        }
+        -(p * p.log2() + (1.0 - p) * (1.0 - p).log2())
+    }
+
+    /// False positive rate at the current fill, `fill_ratio ^ num_hashers`. \
+    /// Exceeds `prob_fp` once more than `data_set_size` items were added.
+    pub fn current_false_positive_rate(&self) -> f64 {
+        self.fill_ratio().powi(self.num_hashers as i32)
+    }
+
+    /// Probability that a batch of `queries` lookups of absent items gets at least one false positive
+    /// at `current_false_positive_rate()`, see `prob_any_false_positive`
+    pub fn prob_any_false_positive_over(&self, queries: usize) -> f64 {
+        Self::prob_any_false_positive(self.current_false_positive_rate(), queries)
+    }
+
+    /// Probability of at least one false positive over `queries` independent lookups of absent
+    /// items, `1 - (1 - per_query_fp)^queries`. For sizing filters to batch workloads: keeping it
+    /// under some bound needs a `per_query_fp` of about `bound / queries`.
+    pub fn prob_any_false_positive(per_query_fp: f64, queries: usize) -> f64 {
+        if queries == 0 {
+            return 0.0;
+        }
+        // 1 - e^(queries * ln(1 - p)), accurate for tiny rates where 1 - p rounds to 1
+        -f64::exp_m1(queries as f64 * f64::ln_1p(-per_query_fp))
+    }
+
+    /// Estimated number of distinct items added, from the fill ratio (Swamidass & Baldi):
+    /// `-(vector_len / num_hashers) * ln(1 - fill_ratio)`. \
+    /// Unlike `inserted`, repeated additions of the same item don't count. Saturates to `usize::MAX`
+    /// once every bit is set.
+    pub fn estimate_cardinality(&self) -> usize {
+        f64::round(self.cardinality_for_set_bits(self.count_set_bits())) as usize
+    }
+
+    /// Swamidass & Baldi estimate for a bit vector of this geometry with set_bits bits set
+    fn cardinality_for_set_bits(&self, set_bits: usize) -> f64 {
+        let vector_len = self.vector_len as f64;
+        -(vector_len / self.num_hashers as f64) * f64::ln(1.0 - set_bits as f64 / vector_len)
+    }
+
+    /// Bytes the filter occupies: the struct itself, hashers included, plus the heap
+    /// capacity of its bit words
+    pub fn memory_usage_bytes(&self) -> usize {
+        let bytes = mem::size_of::<BloomFilter>() + self.bitvec.capacity() * mem::size_of::<u64>();
+        #[cfg(feature = "hll")]
+        let bytes = bytes + self.hyperloglog.heap_bytes();
+        bytes
+    }
+
+    /// Bytes `memory_usage_bytes` would report for a filter built with `new(prob_fp, data_set_size)`,
+    /// computed without allocating: `ceil(vector_len / 64) * 8` bytes of bits plus the struct overhead,
+    /// and with the `hll` feature the distinct counter's registers
+    pub fn estimate_memory_for(prob_fp: f64, data_set_size: usize) -> Result<usize, BloomFilterError> {
+        let (vector_len, _) = Self::optimal_params(prob_fp, data_set_size)?;
+        let bytes = mem::size_of::<BloomFilter>() + Self::words_for(vector_len) * mem::size_of::<u64>();
+        #[cfg(feature = "hll")]
+        let bytes = bytes + HyperLogLog::HEAP_BYTES;
+        Ok(bytes)
+    }
+
+    /// Distinct items added, counted by a HyperLogLog companion updated on every insertion (about
+    /// 0.8% standard error). Stays accurate after the bit vector saturates, when
+    /// `estimate_cardinality` can't tell anymore. \
+    /// The counter is not serialized, so a deserialized filter only counts items added since.
+    #[cfg(feature = "hll")]
+    pub fn precise_cardinality(&self) -> u64 {
+        self.hyperloglog.estimate()
+    }
+
+    /// Configured bits per item, `vector_len / data_set_size`
+    pub fn bits_per_item(&self) -> f64 {
+        self.vector_len as f64 / self.data_set_size as f64
+    }
+
+    /// Bits per item actually in use, `vector_len / estimate_cardinality()`. \
+    /// Above `bits_per_item` the filter is under-utilized, below it over-filled; infinite while empty.
+    pub fn actual_bits_per_item(&self) -> f64 {
+        self.vector_len as f64 / self.estimate_cardinality() as f64
+    }
+
+    /// Roughly how many more distinct items fit before reaching `data_set_size`, and with it the
+    /// target false positive rate: `data_set_size - estimate_cardinality()`, clamped to zero
+    pub fn remaining_capacity(&self) -> usize {
+        self.data_set_size.saturating_sub(self.estimate_cardinality())
+    }
+
+    /// Whether the filter is over-filled, i.e. its current false positive rate exceeds `prob_fp`
+    pub fn needs_rebuild(&self) -> bool {
+        self.current_false_positive_rate() > self.prob_fp
+    }
+
+    /// Suggested `(vector_len, num_hashers)` for a rebuilt filter holding the estimated number of
+    /// items (but never fewer than `data_set_size`) at the original `prob_fp`, validated like
+    /// `optimal_params`. \
+    /// Once every bit is set the fill can't tell how many items there are, and the suggestion
+    /// falls back to the `inserted` count. Nothing is applied; rebuilding needs the original items.
+    pub fn suggest_rebuild_params(&self) -> Result<(usize, usize), BloomFilterError> {
+        Self::optimal_params(self.prob_fp, self.rebuild_data_set_size())
+    }
+
+    /// Item count a rebuilt filter is sized for: `estimate_cardinality`, or `inserted` where the
+    /// estimate is infinite because the filter is saturated, and at least `data_set_size`
+    fn rebuild_data_set_size(&self) -> usize {
+        let estimate = self.cardinality_for_set_bits(self.count_set_bits());
+        let estimate = if estimate.is_finite() { f64::round(estimate) as usize } else { self.inserted };
+        estimate.max(self.data_set_size)
+    }
+
+    /// Fresh filter with the `suggest_rebuild_params` dimensions, filled with items, which should be
+    /// everything added to this one. The one-call fix once `needs_rebuild()` is true. \
+    /// Seed and every setting (index strategy, strict mode, fp_tolerance, empty key handling,
+    /// branchless lookups) are kept, so a keyed filter stays keyed. The new filter is sized for
+    /// the estimated item count, so it starts out at about its capacity and close to `prob_fp`.
+    ///
+    /// Fails like `suggest_rebuild_params`, with `AllocationFailed` when the bits can't be
+    /// allocated, and in strict mode with `CapacityExceeded` if items outnumber the new size.
+    pub fn resize_rebuild<T: BloomHash, I: IntoIterator<Item = T>>(&self, items: I) -> Result<BloomFilter, BloomFilterError> {
+        let (vector_len, num_hashers) = self.suggest_rebuild_params()?;
+        self.rebuild_with(self.rebuild_data_set_size(), vector_len, num_hashers, self.seed, items)
+    }
+
+    /// Fresh filter at this one's prob_fp with the given dimensions and seed, carrying every
+    /// setting (index strategy, strict mode, fp_tolerance, empty key handling, branchless lookups),
+    /// filled with items. The bits are allocated fallibly and strict mode applies to the items.
+    fn rebuild_with<T: BloomHash, I: IntoIterator<Item = T>>(
+        &self,
+        data_set_size: usize,
+        vector_len: usize,
+        num_hashers: usize,
+        seed: u64,
+        items: I,
+    ) -> Result<BloomFilter, BloomFilterError> {
+        let bitvec = Self::try_zeroed_words(Self::words_for(vector_len))?;
+        let mut rebuilt = Self::with_bits(self.prob_fp, data_set_size, vector_len, num_hashers, seed, bitvec);
+        rebuilt.index_strategy = self.index_strategy;
+        rebuilt.strict = self.strict;
+        rebuilt.fp_tolerance = self.fp_tolerance;
+        rebuilt.reject_empty = self.reject_empty;
+        rebuilt.branchless = self.branchless;
+        for item in items {
+            rebuilt.try_add(item)?;
+        }
+        Ok(rebuilt)
+    }
+
+    /// Whether the filter could move to another seed without its items. Bit positions depend on the
+    /// seed and can't be mapped onto another one, so this only holds while no bit is set. \
+    /// Otherwise `reseed_with_items` is the only correct way to change the seed.
+    pub fn can_reseed_losslessly(&self) -> bool {
+        self.count_set_bits() == 0
+    }
+
+    /// Fresh filter with the same dimensions under new_seed, filled with items, which should be
+    /// everything added to this one, e.g. to match another system's filter. \
+    /// Every setting is kept like in `resize_rebuild`; the bits of this filter are not used at all.
+    /// Fails with `AllocationFailed` when the bits can't be allocated, and in strict mode with
+    /// `CapacityExceeded` if items outnumber `data_set_size`.
+    pub fn reseed_with_items<T: BloomHash, I: IntoIterator<Item = T>>(&self, new_seed: u64, items: I) -> Result<BloomFilter, BloomFilterError> {
+        self.rebuild_with(self.data_set_size, self.vector_len, self.num_hashers, new_seed, items)
+    }
+
+    /// Number of hashers that would minimize the false positive rate for the items inserted so far, \
+    /// `k = (vector_len / inserted) * ln 2`. It drifts away from `num_hashers` as the filter fills up.
+    ///
+    /// The hasher count of an existing filter can't change, since the bits already set depend on it,
+    /// so applying this value requires rebuilding the filter from the original items.
+    pub fn optimal_hashes_for_current_fill(&self) -> usize {
+        let inserted = self.inserted.max(1) as f64;
+        let num_hashes = f64::round((self.vector_len as f64 / inserted) * f64::ln(2.0)) as usize;
+        num_hashes.max(1)
+    }
+
+    /// How hashes are mapped onto bit indexes
+    pub fn index_strategy(&self) -> IndexStrategy {
+        self.index_strategy
+    }
+
+    /// Whether other has the same bit geometry (vector length, hasher count, seed and index strategy), \
+    /// i.e. the same data maps to the same bits in both filters
+    pub fn is_compatible_with(&self, other: &BloomFilter) -> bool {
+        self.check_compatible(other).is_ok()
+    }
+
+    /// Why other is not compatible: every mismatching bit geometry parameter or storage length, e.g.
+    /// `"num_hashers differ: 7 vs 5"`, joined with `"; "`. None for compatible filters.
+    pub fn explain_incompatibility(&self, other: &BloomFilter) -> Option<String> {
+        let mut mismatches = Vec::new();
+        if self.vector_len != other.vector_len {
+            mismatches.push(format!("vector_len differ: {} vs {}", self.vector_len, other.vector_len));
+        } else if self.bitvec.len() != other.bitvec.len() {
+            // same vector_len, but trailing words from raw parts, see compact
+            mismatches.push(format!("storage words differ: {} vs {}", self.bitvec.len(), other.bitvec.len()));
+        }
+        if self.num_hashers != other.num_hashers {
+            mismatches.push(format!("num_hashers differ: {} vs {}", self.num_hashers, other.num_hashers));
+        }
+        if self.seed != other.seed {
+            mismatches.push(format!("seed differs: {} vs {}", self.seed, other.seed));
+        }
+        if self.index_strategy != other.index_strategy {
+            mismatches.push(format!("index_strategy differs: {:?} vs {:?}", self.index_strategy, other.index_strategy));
+        }
+
+        if mismatches.is_empty() {
+            return None;
+        }
+        Some(mismatches.join("; "))
+    }
+
+    fn check_compatible(&self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        match self.explain_incompatibility(other) {
+            Some(reason) => Err(BloomFilterError::IncompatibleFilters(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds every item of a compatible filter into this one (bitwise OR). \
+    /// `inserted` becomes the sum of both counts, saturating. Items added to both filters are
+    /// counted twice, so it is an upper bound on the items held, which keeps strict mode and
+    /// `needs_rebuild` on the safe side.
+    pub fn union_with(&mut self, other: &BloomFilter) -> Result<(), BloomFilterError> {
+        self.check_compatible(other)?;
+
+        for (word, other_word) in self.bitvec.iter_mut().zip(&other.bitvec) {
+            *word |= *other_word;
+        }
+        self.inserted = self.inserted.saturating_add(other.inserted);
+
+        #[cfg(feature = "hll")]
+        self.hyperloglog.merge(&other.hyperloglog);
+        Ok(())
+    }
+
+    /// Best-effort union of filters that only differ in their power of two `vector_len`: the larger
+    /// one is folded with `fold_halve` down to the smaller length, then both are ORed. \
+    /// Everything in either filter stays present, but the result has the fill, and with it the
+    /// false positive rate, of a folded filter. Good enough for logging and analytics, not for
+    /// exact membership. Hashers, seed and index strategy must still match.
+    pub fn lossy_union(&self, other: &BloomFilter) -> Result<BloomFilter, BloomFilterError> {
+        let (mut union, smaller) = if self.vector_len >= other.vector_len { (self.clone(), other) } else { (other.clone(), self) };
+        if union.vector_len != smaller.vector_len {
+            for vector_len in [union.vector_len, smaller.vector_len] {
+                if !vector_len.is_power_of_two() {
+                    return Err(BloomFilterError::NotPowerOfTwo(vector_len));
+                }
+            }
+        }
+
+        while union.vector_len > smaller.vector_len {
+            union = union.fold_halve()?;
+        }
+        union.union_with(smaller)?;
+        Ok(union)
+    }
+
+    /// Union of filters that only differ in their power of two `vector_len`, at the largest length. \
+    /// Each smaller filter is projected up with `project_to` before ORing, so nothing is lost and
+    /// every item of every filter stays present. A projected filter keeps its own fill ratio `f`
+    /// across the whole length though, so the result's fill is `1 - (1 - f_1)(1 - f_2)...` over
+    /// *all* inputs: small, dense filters weigh on its false positive rate as much as they did on
+    /// their own, and merging never makes them sparser. Hashers, seed and index strategy must match.
+    pub fn combine_all(filters: Vec<BloomFilter>) -> Result<BloomFilter, BloomFilterError> {
+        let mut filters = filters;
+        let largest = (0..filters.len())
+            .max_by_key(|&index| filters[index].vector_len)
+            .ok_or(BloomFilterError::NoFilters)?;
+        let mut combined = filters.swap_remove(largest);
+
+        for filter in &filters {
+            if filter.vector_len != combined.vector_len {
+                for vector_len in [combined.vector_len, filter.vector_len] {
+                    if !vector_len.is_power_of_two() {
+                        return Err(BloomFilterError::NotPowerOfTwo(vector_len));
+                    }
+                }
+            }
+        }
+        for filter in &filters {
+            combined.union_with(&filter.project_to(combined.vector_len))?;
+        }
+        Ok(combined)
+    }
+
+    /// Indexes of the bits that differ between two compatible filters, in increasing order. \
+    /// Scans the packed words with XOR, so identical regions cost one comparison per 64 bits.
+    /// For huge, very different filters the list can get long; `bit_diff_count` gives just the count.
+    pub fn bit_diff(&self, other: &BloomFilter) -> Result<Vec<usize>, BloomFilterError> {
+        self.check_compatible(other)?;
+
+        let mut diff = Vec::new();
+        for (word_index, (word, other_word)) in self.bitvec.iter().zip(&other.bitvec).enumerate() {
+            let mut differing = word ^ other_word;
+            while differing != 0 {
+                diff.push(word_index * 64 + differing.trailing_zeros() as usize);
+                differing &= differing - 1;
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Number of bits that differ between two compatible filters, without listing them
+    pub fn bit_diff_count(&self, other: &BloomFilter) -> Result<usize, BloomFilterError> {
+        self.check_compatible(other)?;
+
+        Ok(self.bitvec
+            .iter()
+            .zip(&other.bitvec)
+            .map(|(word, other_word)| (word ^ other_word).count_ones() as usize)
+            .sum())
+    }
+
+    /// Number of bits set in both compatible filters, `count_ones(a & b)` word by word, without
+    /// building an intersection filter
+    pub fn intersection_set_bits(&self, other: &BloomFilter) -> Result<usize, BloomFilterError> {
+        self.check_compatible(other)?;
+
+        Ok(self.bitvec
+            .iter()
+            .zip(&other.bitvec)
+            .map(|(word, other_word)| (word & other_word).count_ones() as usize)
+            .sum())
+    }
+
+    /// Estimated number of distinct items added to both compatible filters, by inclusion-exclusion:
+    /// `|A| + |B| - |A ∪ B|`, where the union estimate comes from the OR of both bit vectors. \
+    /// Clamped to zero, since the estimates are noisy for nearly disjoint sets.
+    pub fn estimate_intersection_cardinality(&self, other: &BloomFilter) -> Result<usize, BloomFilterError> {
+        self.check_compatible(other)?;
+
+        let union_set_bits = self.bitvec
+            .iter()
+            .zip(&other.bitvec)
+            .map(|(word, other_word)| (word | other_word).count_ones() as usize)
+            .sum();
+        let estimate = self.cardinality_for_set_bits(self.count_set_bits())
+            + self.cardinality_for_set_bits(other.count_set_bits())
+            - self.cardinality_for_set_bits(union_set_bits);
+        Ok(f64::round(estimate.max(0.0)) as usize)
+    }
+
+    /// Estimated number of distinct items added to self but not to the compatible other filter,
+    /// `|A| - |A ∩ B|`, clamped to zero
+    pub fn estimate_difference_cardinality(&self, other: &BloomFilter) -> Result<usize, BloomFilterError> {
+        let intersection = self.estimate_intersection_cardinality(other)?;
+        Ok(self.estimate_cardinality().saturating_sub(intersection))
+    }
+
+    /// Bitwise XOR of two compatible filters' packed words: the bits set in exactly one of them.
+    /// Between two snapshots of a set, these are the bits touched by items added to only one.
+    pub fn symmetric_difference_bits(&self, other: &BloomFilter) -> Result<Vec<u64>, BloomFilterError> {
+        self.check_compatible(other)?;
+
+        Ok(self.bitvec.iter().zip(&other.bitvec).map(|(word, other_word)| word ^ other_word).collect())
+    }
+
+    /// Estimated number of distinct items added to exactly one of two compatible filters,
+    /// `2 |A ∪ B| - |A| - |B|`, where the union has `(set_bits(A) + set_bits(B) + set_bits(A ^ B)) / 2`
+    /// bits set. Clamped to zero.
+    pub fn symmetric_difference_cardinality(&self, other: &BloomFilter) -> Result<usize, BloomFilterError> {
+        let xor_set_bits: usize = self.symmetric_difference_bits(other)?.iter().map(|word| word.count_ones() as usize).sum();
+        let (set_bits, other_set_bits) = (self.count_set_bits(), other.count_set_bits());
+        let union_set_bits = (set_bits + other_set_bits + xor_set_bits) / 2;
+
+        let estimate = 2.0 * self.cardinality_for_set_bits(union_set_bits)
+            - self.cardinality_for_set_bits(set_bits)
+            - self.cardinality_for_set_bits(other_set_bits);
+        Ok(f64::round(estimate.max(0.0)) as usize)
+    }
+
+    /// Union of self and other when they are compatible. Otherwise their bits can't be combined,
+    /// so a fresh filter is sized for the fallback items, which should cover the contents of both
+    /// filters, and filled with them. It keeps self's `prob_fp`, seed and every setting, like
+    /// `resize_rebuild`. \
+    /// The rebuild fails like `optimal_params`, in particular with `InvalidDataSetSize` when there
+    /// are no fallback items, and with `AllocationFailed` when the bits can't be allocated.
+    pub fn merge_or_rebuild<T: BloomHash, I: IntoIterator<Item = T>>(&self, other: &BloomFilter, fallback_items: I) -> Result<BloomFilter, BloomFilterError> {
+        let mut union = self.clone();
+        if union.union_with(other).is_ok() {
+            return Ok(union);
+        }
+
+        let items: Vec<T> = fallback_items.into_iter().collect();
+        let (vector_len, num_hashers) = Self::optimal_params(self.prob_fp, items.len())?;
+        self.rebuild_with(items.len(), vector_len, num_hashers, self.seed, items)
+    }
+
+    /// Union of many filters in a single pass. \
+    /// All filters are checked for compatibility with the first one before any bits are combined.
+    /// Like `union_with`, `inserted` is the saturating sum of all counts, an upper bound.
+    pub fn union_all<'a, I: IntoIterator<Item = &'a BloomFilter>>(filters: I) -> Result<BloomFilter, BloomFilterError> {
+        let filters: Vec<&BloomFilter> = filters.into_iter().collect();
+        let (first, rest) = filters.split_first().ok_or(BloomFilterError::NoFilters)?;
+
+        for filter in rest {
+            first.check_compatible(filter)?;
+        }
+
+        let mut union = (*first).clone();
+        for (index, word) in union.bitvec.iter_mut().enumerate() {
+            *word |= rest.iter().fold(0, |acc, filter| acc | filter.bitvec[index]);
+        }
+        union.inserted = rest.iter().fold(first.inserted, |inserted, filter| inserted.saturating_add(filter.inserted));
+
+        #[cfg(feature = "hll")]
+        for filter in rest {
+            union.hyperloglog.merge(&filter.hyperloglog);
+        }
+        Ok(union)
+    }
+
+    /// Occupancy histogram of items hashed by the first base hasher into `buckets` bins. \
+    /// A roughly flat histogram means the key type hashes uniformly; heavy skew hints at a poor `Hash` impl.
+    pub fn hash_histogram<T: BloomHash, I: IntoIterator<Item = T>>(&self, items: I, buckets: usize) -> Vec<usize> {
+        let mut histogram = vec![0; buckets];
+        if buckets == 0 {
+            return histogram;
+        }
+
+        for item in items {
+            let mut hasher = self.hash_funcs[0].clone();
+            item.bloom_hash(&mut hasher);
+            histogram[(hasher.finish() % buckets as u64) as usize] += 1;
+        }
+        histogram
+    }
+
+    /// How well items spread over the bit vector: bits set by inserting them into an empty copy of
+    /// this filter, divided by the `vector_len * (1 - (1 - 1/vector_len)^(num_hashers * n))` bits
+    /// expected for `n` distinct items. \
+    /// Near 1.0 the key type hashes well; far below, items collide or cluster. Returns 1.0 without items.
+    pub fn hash_quality_score<T: BloomHash, I: IntoIterator<Item = T>>(&self, items: I) -> f64 {
+        let mut scratch = self.clone();
+        scratch.clear();
+        scratch.strict = false;
+        for item in items {
+            scratch.add(item);
+        }
+        if scratch.inserted == 0 {
+            return 1.0;
+        }
+
+        let vector_len = self.vector_len as f64;
+        let probes = (self.num_hashers * scratch.inserted) as f64;
+        let expected = vector_len * (1.0 - (1.0 - 1.0 / vector_len).powf(probes));
+        scratch.count_set_bits() as f64 / expected
+    }
+
+    /// Fraction of set bits in each of `buckets` equal regions of the bit vector, in order. \
+    /// Meant for rendering a heatmap: with a good hash every region sits near `fill_ratio`, while
+    /// hot and cold regions point at a poorly distributed key type. Regions left empty because
+    /// `buckets > vector_len` read as 0.0.
+    pub fn density_map(&self, buckets: usize) -> Vec<f64> {
+        (0..buckets)
+            .map(|bucket| {
+                let start = bucket * self.vector_len / buckets;
+                let end = (bucket + 1) * self.vector_len / buckets;
+                if start == end {
+                    return 0.0;
+                }
+                let set = (start..end).filter(|&index| self.get_bit(index)).count();
+                set as f64 / (end - start) as f64
+            })
+            .collect()
+    }
+
+    /// Halves the bit vector, so that every item maps onto the folded bits. \
+    /// Requires a power of two `vector_len`, then
+    /// - with `IndexStrategy::Modulo` or `EnhancedDoubleHash`, reducing modulo `vector_len` just masks the low bits, so dropping
+    ///   the top bit of every index ORs the two halves together: bit `i` is `bit[i] | bit[i + half]`
+    /// - with `IndexStrategy::FastRange`, indexes are the high bits of `hash * vector_len`, so halving
+    ///   drops their lowest bit: bit `i` is `bit[2i] | bit[2i + 1]`
+    ///
+    /// Lossy and one way: everything present stays present, but a fill ratio `f` becomes
+    /// `1 - (1 - f)^2`. For a filter at capacity the fill goes from 1/2 to about 3/4, so the false
+    /// positive rate rises from `prob_fp` to roughly `prob_fp^0.42` (1% becomes about 15%).
+    /// Best suited to read-only archival where memory matters more than accuracy.
+    pub fn fold_halve(&self) -> Result<BloomFilter, BloomFilterError> {
+        if self.vector_len < 2 || !self.vector_len.is_power_of_two() {
+            return Err(BloomFilterError::NotPowerOfTwo(self.vector_len));
+        }
+
+        let half = self.vector_len / 2;
+        let mut folded = self.clone();
+        folded.vector_len = half;
+        folded.bitvec = vec![0; Self::words_for(half)];
+        for index in 0..half {
+            let bit = match self.index_strategy {
+                IndexStrategy::Modulo | IndexStrategy::EnhancedDoubleHash => self.get_bit(index) | self.get_bit(index + half),
+                IndexStrategy::FastRange => self.get_bit(2 * index) | self.get_bit(2 * index + 1),
+            };
+            if bit {
+                folded.set_bit(index);
+            }
+        }
+        Ok(folded)
+    }
+
+    /// Inverse of repeated `fold_halve`: copies the bits onto a power of two multiple of `vector_len`,
+    /// so that every index an item maps to in the longer vector is set if its index in this one is
+    /// - with `IndexStrategy::Modulo` or `EnhancedDoubleHash`, bit `i` is `bit[i % vector_len]`,
+    ///   the vector repeated
+    /// - with `IndexStrategy::FastRange`, bit `i` is `bit[i / factor]`, every bit stretched `factor` times
+    fn project_to(&self, vector_len: usize) -> BloomFilter {
+        let factor = vector_len / self.vector_len;
+        let mut projected = self.clone();
+        projected.vector_len = vector_len;
+        projected.bitvec = vec![0; Self::words_for(vector_len)];
+        for index in 0..vector_len {
+            let source = match self.index_strategy {
+                IndexStrategy::Modulo | IndexStrategy::EnhancedDoubleHash => index % self.vector_len,
+                IndexStrategy::FastRange => index / factor,
+            };
+            if self.get_bit(source) {
+                projected.set_bit(index);
+            }
+        }
+        projected
+    }
+
+    /// Whether at least `m` of the compatible filters (e.g. replicas) report data as present. \
+    /// Since the filters share their bit geometry, data is hashed only once.
+    pub fn majority_contains<T: BloomHash>(filters: &[&BloomFilter], data: T, m: usize) -> Result<bool, BloomFilterError> {
+        let (first, rest) = filters.split_first().ok_or(BloomFilterError::NoFilters)?;
+        for filter in rest {
+            first.check_compatible(filter)?;
+        }
+
+        let indexes: Vec<usize> = first.indexes(data).collect();
+        let present = filters
+            .iter()
+            .filter(|filter| indexes.iter().all(|&index| filter.get_bit(index)))
+            .count();
+        Ok(present >= m)
+    }
+
+    /// Number of u64 words needed to hold vector_len bits
+    fn words_for(vector_len: usize) -> usize {
+        vector_len.div_ceil(64)
+    }
+
+    /// Bits outside of storage read as clear
+    fn get_bit(&self, index: usize) -> bool {
+        self.bitvec.get(index / 64).is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    /// Sets a bit, returning whether it was clear before
+    fn set_bit(&mut self, index: usize) -> bool {
+        let word = &mut self.bitvec[index / 64];
+        let mask = 1 << (index % 64);
+        let was_clear = *word & mask == 0;
+        *word |= mask;
+        was_clear
+    }
+
+    fn clear_bit(&mut self, index: usize) {
+        self.bitvec[index / 64] &= !(1 << (index % 64));
+    }
+
+    /// Clears bits `start_bit..end_bit`, zeroing whole words at once and masking the partial
+    /// words at either boundary. This is the primitive for cheap partial resets.
+    fn clear_range(&mut self, start_bit: usize, end_bit: usize) {
+        let end_bit = end_bit.min(self.vector_len);
+        if start_bit >= end_bit {
+            return;
+        }
+
+        let (first_word, last_word) = (start_bit / 64, (end_bit - 1) / 64);
+        let low_mask = u64::MAX << (start_bit % 64);   // bits at or above start_bit in the first word
+        let high_mask = u64::MAX >> (63 - (end_bit - 1) % 64);   // bits below end_bit in the last word
+
+        if first_word == last_word {
+            self.bitvec[first_word] &= !(low_mask & high_mask);
+            return;
+        }
+        self.bitvec[first_word] &= !low_mask;
+        for word in &mut self.bitvec[first_word + 1..last_word] {
+            *word = 0;
+        }
+        self.bitvec[last_word] &= !high_mask;
+    }
+
+    /// Sets pseudo-random bits until at least `density` of the bits are set, for benchmarks that
+    /// need a filter at a known fill without inserting real items. \
+    /// Deterministic for a given seed; a filter already at or above density is left as is.
+    #[cfg(any(test, feature = "testutil"))]
+    pub fn fill_random_to_density(&mut self, density: f64, seed: u64) {
+        let target = f64::ceil(density.clamp(0.0, 1.0) * self.vector_len as f64) as usize;
+        let mut set_bits = self.count_set_bits();
+        let mut state = seed;
+        while set_bits < target {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+
+            if self.set_bit((z % self.vector_len as u64) as usize) {
+                set_bits += 1;
+            }
+        }
+    }
+
+    /// Removes every item, keeping the dimensions and seed
+    pub fn clear(&mut self) {
+        self.clear_range(0, self.vector_len);
+        self.inserted = 0;
+
+        #[cfg(feature = "hll")]
+        self.hyperloglog.clear();
+    }
+
+    /// Resizes the backing storage to exactly the words `vector_len` needs and releases any spare
+    /// capacity, e.g. for filters reassembled with `from_raw_parts` from an oversized buffer. \
+    /// Trailing words beyond `vector_len` are dropped, missing ones are added as clear bits.
+    pub fn compact(&mut self) {
+        self.bitvec.resize(Self::words_for(self.vector_len), 0);
+        self.bitvec.shrink_to_fit();
+    }
+
+    /// Labels of the filters that (probably) contain data, e.g. which tenants have seen a key. \
+    /// The filters don't need to be compatible; each one is queried on its own.
+    pub fn which_contain<'a, T: BloomHash>(filters: &'a [(&'a str, &'a BloomFilter)], data: T) -> Vec<&'a str> {
+        filters
+            .iter()
+            .filter(|(_, filter)| filter.contains_ref(&data))
+            .map(|(label, _)| *label)
+            .collect()
+    }
+
+    /// The `num_hashers` bit indexes data maps to, in probe order and after the index strategy. \
+    /// Indexes can repeat when two probes collide. Empty with `reject_empty` for an empty key,
+    /// which maps to no bits.
+    #[must_use]
+    pub fn hash_positions<T: BloomHash>(&self, data: T) -> Vec<usize> {
+        self.accepted_indexes(data).into_iter().flatten().collect()
+    }
+
+    /// Calls f with every bit index `add(data)` would set, without touching this filter's bits,
+    /// so that custom storage backends (remote, GPU, ...) can reuse the index computation. \
+    /// Like `add`, never calls f for an empty key rejected by `reject_empty`.
+    pub fn add_visit<T: BloomHash, F: FnMut(usize)>(&self, data: T, f: F) {
+        self.accepted_indexes(data).into_iter().flatten().for_each(f);
+    }
+
+    /// Lookup counterpart of `add_visit`: asks f whether each bit index `contains(data)` would
+    /// read is set, stopping at the first false. \
+    /// Like `contains`, an empty key rejected by `reject_empty` is absent without calling f.
+    #[must_use]
+    pub fn contains_visit<T: BloomHash, F: FnMut(usize) -> bool>(&self, data: T, f: F) -> bool {
+        self.accepted_indexes(data).is_some_and(|mut indexes| indexes.all(f))
+    }
+
+    /// The indexes of data, or None for an empty key rejected by `reject_empty`
+    fn accepted_indexes<T: BloomHash>(&self, data: T) -> Option<impl Iterator<Item = usize>> {
+        if self.rejects(&data) {
+            return None;
+        }
+        Some(self.indexes(data))
+    }
+
+    /// Computes the two base hashes of data, one per base hasher. \
+    /// Together with `add_hashes`/`contains_hashes` this lets callers hash an item once and reuse
+    /// the result across lookups or compatible filters.
+    pub fn compute_hashes<T: BloomHash>(&self, data: T) -> (u64, u64) {
+        self.hashes_of(&data)
+    }
+
+    pub(crate) fn hashes_of<T: BloomHash + ?Sized>(&self, data: &T) -> (u64, u64) {
+        let [mut hasher_1, mut hasher_2] = self.hash_funcs.clone();
+        data.bloom_hash(&mut hasher_1);
+        data.bloom_hash(&mut hasher_2);
+        (hasher_1.finish(), hasher_2.finish())
+    }
+
+    /// With `reject_empty`, whether data is an empty key, see `BloomHash::is_empty_key`. \
+    /// Decided on the key before hashing: hashes can't tell `""` from `255u8`, which write the same bytes.
+    pub(crate) fn rejects<T: BloomHash + ?Sized>(&self, data: &T) -> bool {
+        self.reject_empty && data.is_empty_key()
+    }
+
+    /// Base hashes of the raw bytes, without the length prefix `Hash` adds for slices
+    fn byte_hashes(&self, bytes: &[u8]) -> (u64, u64) {
+        let [mut hasher_1, mut hasher_2] = self.hash_funcs.clone();
+        hasher_1.write(bytes);
+        hasher_2.write(bytes);
+        (hasher_1.finish(), hasher_2.finish())
+    }
+
+    fn indexes<T: BloomHash>(&self, data: T) -> impl Iterator<Item = usize> {
+        let (hash_1, hash_2) = self.compute_hashes(data);
+        self.indexes_from_hashes(hash_1, hash_2)
+    }
+
+    /// Bit indexes derived with Kirsch-Mitzenmacher double hashing \
+    /// g_i(x) = h1(x) + i * h2(x) (plus i^3 for `EnhancedDoubleHash`), so only two hashes are
+    /// computed regardless of num_hashers
+    fn indexes_from_hashes(&self, hash_1: u64, hash_2: u64) -> impl Iterator<Item = usize> {
+        let vector_len = self.vector_len;
+        let index_strategy = self.index_strategy;
+
+        (0..self.num_hashers as u64)
+            .map(move |i| index_strategy.index(index_strategy.probe(hash_1, hash_2, i), vector_len))
+    }
+
+    /// Optimal `(vector_len, num_hashers)` for the given parameters, i.e. the dimensions \
+    /// `new` would pick, computed without allocating a filter
+    pub fn optimal_params(prob_fp: f64, data_set_size: usize) -> Result<(usize, usize), BloomFilterError> {
+        if !prob_fp.is_finite() || prob_fp <= 0.0 || prob_fp >= 1.0 {
+            return Err(BloomFilterError::InvalidProbability(prob_fp));
+        }
+        if data_set_size == 0 {
+            return Err(BloomFilterError::InvalidDataSetSize);
+        }
+        if Self::optimal_vector_bits(prob_fp, data_set_size) >= MAX_VECTOR_LEN as f64 {
+            return Err(BloomFilterError::FilterTooLarge(data_set_size));
+        }
+
+        Ok((Self::get_optimal_vector_len(prob_fp, data_set_size), Self::get_optimal_num_hashes(prob_fp)))
+    }
+
+    /// Planning table of `(prob_fp, vector_len, num_hashers, bytes)` for data_set_size items at each
+    /// candidate rate, in the given order, where bytes are the packed bit words. \
+    /// Rates `optimal_params` rejects are skipped.
+    pub fn sizing_table(data_set_size: usize, fp_rates: &[f64]) -> Vec<(f64, usize, usize, usize)> {
+        fp_rates
+            .iter()
+            .filter_map(|&prob_fp| {
+                let (vector_len, num_hashers) = Self::optimal_params(prob_fp, data_set_size).ok()?;
+                Some((prob_fp, vector_len, num_hashers, Self::words_for(vector_len) * 8))
+            })
+            .collect()
+    }
+
+    fn get_optimal_num_hashes(prob_fp: f64) -> usize {
+        let ln_2 = f64::ln(2.0);
+        let ln_prob_fp = f64::ln(prob_fp);
+        let num_hashes = f64::ceil(-(ln_prob_fp/ln_2)) as usize;
+        num_hashes.max(1)
+    }
+
+    fn get_optimal_vector_len(prob_fp: f64, data_set_size: usize) -> usize {
+        let vector_len = f64::ceil(Self::optimal_vector_bits(prob_fp, data_set_size)) as usize;
+        vector_len.max(1)
+    }
+
+    fn optimal_vector_bits(prob_fp: f64, data_set_size: usize) -> f64 {
+        let ln_2 = f64::ln(2.0);
+        let ln_prob_fp = f64::ln(prob_fp);
+        -(((data_set_size as f64) * ln_prob_fp)/(ln_2.powi(2)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let mut bloom_filter = BloomFilter::new(0.001, 100);
+        bloom_filter.add("cat");
+        assert!(bloom_filter.contains("cat"));
+    }
+
+    #[test]
+    fn simple_test_2() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        assert!(!bloom_filter.contains("cat"));
+        assert!(!bloom_filter.contains("dog"));
+        bloom_filter.add(String::from("cat"));
+        bloom_filter.add("dog");
+        bloom_filter.add("komal");
+        bloom_filter.add("animal");
+        assert!(bloom_filter.contains(String::from("cat")));
+        assert!(bloom_filter.contains("dog"));
+        assert!(!bloom_filter.contains("monkey"));
+        assert!(bloom_filter.contains("komal"));
+        assert!(!bloom_filter.contains("fox"));
+        assert!(bloom_filter.contains("animal"));
+    }
+
+    #[test]
+    fn single_item_data_set() {
+        for prob_fp in [0.5, 0.1, 0.01, 0.0001] {
+            let mut bloom_filter = BloomFilter::new(prob_fp, 1);
+            assert!(bloom_filter.vector_len >= 1);
+            assert!(bloom_filter.num_hashers >= 1);
+
+            bloom_filter.add("cat");
+            assert!(bloom_filter.contains("cat"));
+        }
+    }
+
+    #[test]
+    fn from_hashset_holds_every_element() {
+        let set: HashSet<String> = (0..100).map(|i| format!("item {}", i)).collect();
+        let bloom_filter = BloomFilter::from_hashset(&set, 0.01).unwrap();
+
+        assert!(set.iter().all(|item| bloom_filter.contains(item)));
+        assert_eq!(bloom_filter.data_set_size, 100);
+        assert_eq!(bloom_filter.vector_len, BloomFilter::optimal_params(0.01, 100).unwrap().0);
+        assert_eq!(BloomFilter::from_hashset(&HashSet::<u32>::new(), 0.01).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+    }
+
+    #[test]
+    fn keyed_filters_are_reproducible() {
+        let mut first = BloomFilter::new_with_key(0.01, 100, b"secret");
+        let mut second = BloomFilter::new_with_key(0.01, 100, b"secret");
+        let mut other_key = BloomFilter::new_with_key(0.01, 100, b"other secret");
+        for bloom_filter in [&mut first, &mut second, &mut other_key] {
+            bloom_filter.add("cat");
+            assert!(bloom_filter.contains("cat"));
+        }
+
+        assert!(first.is_compatible_with(&second));
+        assert_eq!(first.bitvec, second.bitvec);
+        assert!(!first.is_compatible_with(&other_key));
+        assert_ne!(first.bitvec, other_key.bitvec);
+    }
+
+    #[test]
+    fn salted_replicas_map_items_differently() {
+        let mut first = BloomFilter::new_with_salt(0.01, 100, 1);
+        let mut second = BloomFilter::new_with_salt(0.01, 100, 2);
+        for bloom_filter in [&mut first, &mut second] {
+            bloom_filter.add("cat");
+            assert!(bloom_filter.contains("cat"));
+        }
+
+        assert_ne!(first.hash_positions("cat"), second.hash_positions("cat"));
+        assert!(!first.is_compatible_with(&second));
+        assert_eq!(first.hash_positions("cat"), BloomFilter::new_with_salt(0.01, 100, 1).hash_positions("cat"));
+    }
+
+    #[test]
+    fn try_add_reports_out_of_range_index() {
+        let mut source = BloomFilter::new(0.01, 100);
+        source.add("cat");
+        let rebuilt = BloomFilter::from_raw_parts(0.01, 100, source.vector_len, source.num_hashers, source.seed, source.bitvec.clone()).unwrap();
+        assert!(rebuilt.contains("cat"));
+
+        // from_raw_parts rejects short storage, so break the invariant by hand
+        let mut truncated = BloomFilter::with_dimensions(0.01, 100, 100_000, 7, 42);
+        truncated.bitvec.truncate(1);
+        let out_of_range = (0..100).find(|i| truncated.hash_positions(i).iter().any(|&index| index >= 64)).unwrap();
+        assert!(!truncated.contains(out_of_range));
+        match truncated.try_add(out_of_range) {
+            Err(BloomFilterError::IndexOutOfRange { index, storage_bits }) => {
+                assert!(index >= 64);
+                assert_eq!(storage_bits, 64);
+            }
+            other => panic!("expected out of range error, got {:?}", other),
+        }
+        assert_eq!(truncated.count_set_bits(), 0);
+        assert_eq!(truncated.inserted(), 0);
+    }
+
+    #[test]
+    fn parts_round_trip_separately() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 100).index_strategy(IndexStrategy::FastRange).build().unwrap();
+        for i in 0..50 {
+            bloom_filter.add(i);
+        }
+        let original = bloom_filter.clone();
+
+        let (config, bits) = bloom_filter.into_parts();
+        let stored_config = config;
+        let stored_bits = bits.clone();
+        assert_eq!(stored_config.index_strategy, IndexStrategy::FastRange);
+        assert_eq!(stored_config.inserted, 50);
+
+        let restored = BloomFilter::from_parts(stored_config, stored_bits).unwrap();
+        assert!((0..50).all(|i| restored.contains(i)));
+        assert!(restored.is_compatible_with(&original));
+        assert_eq!(restored.bitvec, original.bitvec);
+
+        assert!(matches!(BloomFilter::from_parts(config, vec![0; 1]), Err(BloomFilterError::IndexOutOfRange { .. })));
+    }
+
+    #[test]
+    fn validate_reports_each_broken_invariant() {
+        assert!(BloomFilter::new(0.01, 100).validate().is_ok());
+        assert!(BloomFilter::from_raw_parts(0.01, 100, 100, 3, 42, vec![0; 5]).is_ok());
+
+        assert_eq!(BloomFilter::from_raw_parts(0.01, 100, 0, 3, 42, vec![0; 1]).unwrap_err(),
+            BloomFilterError::InvalidDimensions { vector_len: 0, num_hashers: 3 });
+        assert_eq!(BloomFilter::from_raw_parts(0.01, 100, 100, 0, 42, vec![0; 2]).unwrap_err(),
+            BloomFilterError::InvalidDimensions { vector_len: 100, num_hashers: 0 });
+        assert_eq!(BloomFilter::from_raw_parts(0.01, 100, 100, 3, 42, vec![0; 1]).unwrap_err(),
+            BloomFilterError::IndexOutOfRange { index: 99, storage_bits: 64 });
+        for bits in [vec![0, 1 << 36], vec![0, 0, 1], vec![0, 0, 0, 1 << 63]] {
+            assert!(matches!(BloomFilter::from_raw_parts(0.01, 100, 100, 3, 42, bits), Err(BloomFilterError::InvalidFormat(_))));
+        }
+
+        let mut cleared_hashers = BloomFilter::new(0.01, 100);
+        cleared_hashers.num_hashers = 0;
+        assert!(matches!(cleared_hashers.validate(), Err(BloomFilterError::InvalidDimensions { .. })));
+    }
+
+    #[test]
+    fn memory_estimate_matches_built_filter() {
+        for (prob_fp, data_set_size) in [(0.01, 100), (0.001, 10_000), (0.5, 1)] {
+            let bloom_filter = BloomFilter::new(prob_fp, data_set_size);
+            assert_eq!(BloomFilter::estimate_memory_for(prob_fp, data_set_size).unwrap(), bloom_filter.memory_usage_bytes());
+        }
+        assert!(BloomFilter::estimate_memory_for(0.01, 1_000_000).unwrap() > 1_000_000);
+        assert_eq!(BloomFilter::estimate_memory_for(0.01, 0).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+    }
+
+    #[test]
+    fn memory_budget_bounds_the_bits() {
+        let bloom_filter = BloomFilter::for_memory_budget(1000, 1024).unwrap();
+        assert!(bloom_filter.bitvec.len() * 8 <= 1024);
+        assert_eq!(bloom_filter.vector_len, 1024 * 8);
+        let expected_hashers = f64::round(8192.0 / 1000.0 * f64::ln(2.0)) as usize;
+        assert_eq!(bloom_filter.num_hashers, expected_hashers);
+        assert!(bloom_filter.prob_fp > 0.0 && bloom_filter.prob_fp < 0.05);
+
+        let odd_budget = BloomFilter::for_memory_budget(10, 100).unwrap();
+        assert!(odd_budget.bitvec.len() * 8 <= 100);
+        assert_eq!(BloomFilter::for_memory_budget(10, 7).unwrap_err(), BloomFilterError::MemoryBudgetTooSmall(7));
+        assert_eq!(BloomFilter::for_memory_budget(0, 1024).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+        assert_eq!(BloomFilter::for_memory_budget(10, usize::MAX).unwrap_err(), BloomFilterError::FilterTooLarge(10));
+        assert!(matches!(BloomFilter::for_memory_budget(10, usize::MAX / 64), Err(BloomFilterError::AllocationFailed(_))));
+
+        // 8 million bits for one item would ask for about 5.8 million hashers
+        let mut roomy = BloomFilter::for_memory_budget(1, 1 << 20).unwrap();
+        assert_eq!(roomy.num_hashers, MAX_BUDGET_HASHERS);
+        assert!(roomy.prob_fp > 0.0 && roomy.prob_fp < 1.0, "prob_fp {}", roomy.prob_fp);
+        assert!(BloomFilter::optimal_params(roomy.prob_fp, 1).is_ok());
+        roomy.add("cat");
+        assert!(roomy.contains("cat"));
+    }
+
+    #[test]
+    fn hasher_storage_is_independent_of_num_hashers() {
+        let mut few_hashers = BloomFilter::new(0.5, 100);
+        let mut many_hashers = BloomFilter::new(0.000001, 100);
+        assert!(few_hashers.num_hashers < many_hashers.num_hashers);
+        assert_eq!(few_hashers.hash_funcs.len(), 2);
+        assert_eq!(many_hashers.hash_funcs.len(), 2);
+
+        for bloom_filter in [&mut few_hashers, &mut many_hashers] {
+            bloom_filter.add("cat");
+            bloom_filter.add("dog");
+            assert!(bloom_filter.contains("cat"));
+            assert!(bloom_filter.contains("dog"));
+        }
+        assert!(!many_hashers.contains("monkey"));
+    }
+
+    #[test]
+    fn union_all_compatible_filters() {
+        let empty = BloomFilter::new(0.01, 100);
+        let mut filters = vec![empty.clone(); 5];
+        for (i, bloom_filter) in filters.iter_mut().enumerate() {
+            bloom_filter.add(i);
+            bloom_filter.add(format!("item-{}", i));
+        }
+
+        let union = BloomFilter::union_all(&filters).unwrap();
+        for i in 0..filters.len() {
+            assert!(union.contains(i));
+            assert!(union.contains(format!("item-{}", i)));
+        }
+        assert_eq!(union.inserted(), 10);
+    }
+
+    #[test]
+    fn union_counts_the_items_of_both_filters() {
+        let mut strict = BloomFilter::builder(0.01, 10).strict(true).build().unwrap();
+        let mut other = strict.clone();
+        for i in 0..6 {
+            strict.add(i);
+            other.add(i + 100);
+        }
+
+        let mut union = strict.clone();
+        union.union_with(&other).unwrap();
+        assert_eq!(union.inserted(), 12);
+        assert_eq!(union.try_add("cat"), Err(BloomFilterError::CapacityExceeded(10)));
+
+        let mut saturated = strict.clone();
+        saturated.inserted = usize::MAX;
+        assert_eq!(BloomFilter::union_all([&saturated, &other]).unwrap().inserted(), usize::MAX);
+    }
+
+    #[test]
+    fn union_all_rejects_incompatible_filter() {
+        let empty = BloomFilter::new(0.01, 100);
+        let mut filters = vec![empty.clone(); 4];
+        filters.push(BloomFilter::new(0.01, 50));
+
+        match BloomFilter::union_all(&filters) {
+            Err(BloomFilterError::IncompatibleFilters(reason)) => assert!(reason.contains("vector_len")),
+            other => panic!("expected incompatible filters error, got {:?}", other),
+        }
+        assert_eq!(BloomFilter::union_all(Vec::new()).unwrap_err(), BloomFilterError::NoFilters);
+
+        let padded = BloomFilter::from_raw_parts(0.01, 100, empty.vector_len, empty.num_hashers, empty.seed, vec![0; empty.bitvec.len() + 2]).unwrap();
+        match BloomFilter::union_all([&empty, &padded]) {
+            Err(BloomFilterError::IncompatibleFilters(reason)) => assert!(reason.contains("storage words")),
+            other => panic!("expected incompatible filters error, got {:?}", other),
+        }
+        let mut compacted = padded.clone();
+        compacted.compact();
+        assert!(BloomFilter::union_all([&empty, &compacted]).is_ok());
+    }
+
+    #[test]
+    fn optimal_params_match_constructed_filter() {
+        for (prob_fp, data_set_size) in [(0.5, 100), (0.01, 1000), (0.0001, 1)] {
+            let bloom_filter = BloomFilter::new(prob_fp, data_set_size);
+            assert_eq!(
+                BloomFilter::optimal_params(prob_fp, data_set_size),
+                Ok((bloom_filter.vector_len, bloom_filter.num_hashers))
+            );
+        }
+
+        assert_eq!(BloomFilter::optimal_params(1.5, 100), Err(BloomFilterError::InvalidProbability(1.5)));
+        assert_eq!(BloomFilter::optimal_params(0.01, 0), Err(BloomFilterError::InvalidDataSetSize));
+    }
+
+    #[test]
+    fn contains_exact_only_consults_the_store_for_possible_positives() {
+        let store: HashSet<u32> = (0..50).collect();
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        for item in &store {
+            bloom_filter.add(item);
+        }
+
+        let mut lookups = 0;
+        for item in 0..1000u32 {
+            let exact = bloom_filter.contains_exact(&item, |item| {
+                lookups += 1;
+                store.contains(item)
+            });
+            assert_eq!(exact, store.contains(&item));
+        }
+        let possible_positives = (0..1000u32).filter(|item| bloom_filter.contains(item)).count();
+        assert_eq!(lookups, possible_positives);
+        assert!(lookups < 100);
+    }
+
+    #[test]
+    fn prob_any_false_positive_grows_with_queries() {
+        assert!((BloomFilter::prob_any_false_positive(0.01, 1) - 0.01).abs() < 1e-12);
+        assert_eq!(BloomFilter::prob_any_false_positive(0.01, 0), 0.0);
+        assert!((BloomFilter::prob_any_false_positive(0.01, 100) - (1.0 - 0.99f64.powi(100))).abs() < 1e-12);
+
+        let probabilities: Vec<f64> = [1, 10, 100, 1000, 10_000].iter().map(|&queries| BloomFilter::prob_any_false_positive(0.01, queries)).collect();
+        assert!(probabilities.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(probabilities[4] > 0.999_999);
+        assert!(BloomFilter::prob_any_false_positive(1e-18, 1000) > 0.0);
+
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        assert_eq!(bloom_filter.prob_any_false_positive_over(1000), 0.0);
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+        let rate = bloom_filter.current_false_positive_rate();
+        assert_eq!(bloom_filter.prob_any_false_positive_over(1), BloomFilter::prob_any_false_positive(rate, 1));
+    }
+
+    #[test]
+    fn sizing_table_grows_with_accuracy() {
+        let table = BloomFilter::sizing_table(1000, &[0.1, 0.01, 0.001]);
+        assert_eq!(table.len(), 3);
+        for pair in table.windows(2) {
+            assert!(pair[1].1 > pair[0].1);
+            assert!(pair[1].2 > pair[0].2);
+        }
+        assert_eq!(table[1], (0.01, 9586, 7, 1200));
+        assert!(BloomFilter::sizing_table(1000, &[f64::NAN, 2.0]).is_empty());
+    }
+
+    #[test]
+    fn unaddressable_sizes_are_rejected() {
+        assert_eq!(BloomFilter::try_new(1e-300, usize::MAX).unwrap_err(), BloomFilterError::FilterTooLarge(usize::MAX));
+        assert_eq!(BloomFilter::builder(0.01, usize::MAX / 8).build().unwrap_err(), BloomFilterError::FilterTooLarge(usize::MAX / 8));
+        assert!(BloomFilter::optimal_params(0.01, 1 << 40).is_ok());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn allocation_failure_is_an_error() {
+        // About 26 PB of bits: addressable, but beyond any real address space
+        let data_set_size = 1 << 57;
+        let expected_bytes = BloomFilter::words_for(BloomFilter::optimal_params(0.5, data_set_size).unwrap().0) * 8;
+        assert_eq!(BloomFilter::try_new(0.5, data_set_size).unwrap_err(), BloomFilterError::AllocationFailed(expected_bytes));
+    }
+
+    #[test]
+    fn non_finite_or_negative_prob_fp_is_rejected() {
+        for prob_fp in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.5] {
+            assert!(matches!(BloomFilter::try_new(prob_fp, 100), Err(BloomFilterError::InvalidProbability(_))));
+            assert!(matches!(BloomFilter::builder(prob_fp, 100).build(), Err(BloomFilterError::InvalidProbability(_))));
+            assert!(matches!(BloomFilter::optimal_params(prob_fp, 100), Err(BloomFilterError::InvalidProbability(_))));
+        }
+        assert_eq!(BloomFilter::try_new(0.01, 0).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+        assert!(BloomFilter::try_new(0.01, 100).is_ok());
+    }
+
+    #[test]
+    fn branchless_lookup_agrees_with_short_circuiting() {
+        for density in [0.1, 0.5, 0.9, 0.99] {
+            let mut bloom_filter = BloomFilter::with_dimensions(0.01, 100, 1000, 7, 7);
+            bloom_filter.fill_random_to_density(density, 3);
+            let mut branchless = bloom_filter.clone();
+            branchless.branchless = true;
+
+            for i in 0..1000 {
+                let hashes = bloom_filter.compute_hashes(i);
+                assert_eq!(branchless.contains_hashes(hashes), bloom_filter.contains_hashes(hashes));
+            }
+        }
+        assert!(BloomFilter::builder(0.01, 100).branchless_contains(true).build().unwrap().branchless);
+    }
+
+    #[test]
+    fn empty_keys_are_hashed_unless_rejected() {
+        let mut default = BloomFilter::builder(0.01, 10).build().unwrap();
+        assert_eq!(default.try_add(""), Ok(true));
+        assert!(default.contains(""));
+        assert_eq!(default.inserted(), 1);
+
+        let mut rejecting = BloomFilter::builder(0.01, 10).reject_empty(true).build().unwrap();
+        assert_eq!(rejecting.try_add(""), Ok(false));
+        rejecting.add(String::new());
+        rejecting.add_bytes(b"");
+        assert_eq!(rejecting.inserted(), 0);
+        assert_eq!(rejecting.count_set_bits(), 0);
+        assert!(!rejecting.contains(""));
+        assert!(!rejecting.contains_bytes(b""));
+
+        assert!(rejecting.hash_positions("").is_empty());
+        rejecting.add_visit("", |_| panic!("visited an empty key"));
+        assert!(!rejecting.contains_visit("", |_| true));
+        assert_eq!(default.hash_positions("").len(), default.num_hashers);
+
+        rejecting.add("cat");
+        assert!(rejecting.contains("cat"));
+        assert!(rejecting.contains_visit("cat", |index| rejecting.get_bit(index)));
+    }
+
+    #[test]
+    fn keys_writing_empty_key_bytes_are_not_rejected() {
+        let mut rejecting = BloomFilter::builder(0.01, 10).reject_empty(true).build().unwrap();
+        rejecting.add(0u64);
+        rejecting.add(0usize);
+        rejecting.add(255u8);
+        rejecting.add(());
+        assert_eq!(rejecting.inserted(), 4);
+
+        // 255u8 feeds the hashers the very bytes "" does, only the key itself tells them apart
+        assert_eq!(rejecting.compute_hashes(255u8), rejecting.compute_hashes(""));
+        assert!(rejecting.contains(0u64) && rejecting.contains(0usize) && rejecting.contains(255u8) && rejecting.contains(()));
+        assert!(rejecting.contains_batch(&[255u8]) == [true] && rejecting.contains_ref(&0u64));
+        assert!(!rejecting.contains(""));
+        assert_eq!(rejecting.contains_batch(&[""]), [false]);
+    }
+
+    #[test]
+    fn strict_mode_rejects_inserts_past_capacity() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 10).strict(true).build().unwrap();
+        for i in 0..10 {
+            assert!(bloom_filter.try_add(i).is_ok());
+        }
+        assert_eq!(bloom_filter.inserted(), 10);
+        assert_eq!(bloom_filter.try_add(10), Err(BloomFilterError::CapacityExceeded(10)));
+        let set_bits = bloom_filter.count_set_bits();
+        assert_eq!(bloom_filter.try_add_bytes(b"fox"), Err(BloomFilterError::CapacityExceeded(10)));
+        assert_eq!(bloom_filter.count_set_bits(), set_bits);
+        assert_eq!(bloom_filter.inserted(), 10);
+
+        let mut lenient = BloomFilter::builder(0.01, 10).build().unwrap();
+        for i in 0..20 {
+            assert!(lenient.try_add(i).is_ok());
+        }
+        assert!(lenient.contains(15));
+    }
+
+    #[test]
+    fn over_filled_filter_suggests_larger_params() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        for i in 0..50 {
+            bloom_filter.add(i);
+        }
+        assert!(!bloom_filter.needs_rebuild());
+        assert!(bloom_filter.estimate_cardinality().abs_diff(50) <= 5);
+        assert_eq!(bloom_filter.suggest_rebuild_params(), Ok((bloom_filter.vector_len, bloom_filter.num_hashers)));
+
+        for i in 50..400 {
+            bloom_filter.add(i);
+        }
+        assert!(bloom_filter.needs_rebuild());
+        let (vector_len, num_hashers) = bloom_filter.suggest_rebuild_params().unwrap();
+        assert!(vector_len > bloom_filter.vector_len);
+        assert_eq!(num_hashers, bloom_filter.num_hashers);
+
+        let mut saturated = BloomFilter::new(0.5, 1);
+        for i in 0..50 {
+            saturated.add(i);
+        }
+        assert_eq!(saturated.count_set_bits(), saturated.vector_len);
+        assert_eq!(saturated.suggest_rebuild_params(), BloomFilter::optimal_params(0.5, 50));
+        assert!(matches!(BloomFilter::new(f64::NAN, 10).suggest_rebuild_params(), Err(BloomFilterError::InvalidProbability(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "hll")]
+    fn precise_cardinality_survives_saturation() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 1000, b"key");
+        for i in 0..100_000 {
+            bloom_filter.add(i);
+        }
+
+        assert_eq!(bloom_filter.estimate_cardinality(), usize::MAX);
+        let precise = bloom_filter.precise_cardinality();
+        assert!(precise.abs_diff(100_000) <= 2_000, "precise cardinality {}", precise);
+
+        bloom_filter.clear();
+        assert_eq!(bloom_filter.precise_cardinality(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "hll")]
+    fn union_all_merges_distinct_counters() {
+        let mut filters: Vec<BloomFilter> = (0..3).map(|_| BloomFilter::new_with_key(0.01, 1000, b"key")).collect();
+        for (offset, filter) in filters.iter_mut().enumerate() {
+            for i in 0..1000usize {
+                filter.add(offset * 1000 + i);
+            }
+        }
+
+        let union = BloomFilter::union_all(&filters).unwrap();
+        assert!(union.precise_cardinality().abs_diff(3000) <= 100, "precise cardinality {}", union.precise_cardinality());
+        assert!(union.memory_usage_bytes() >= union.bitvec.capacity() * 8 + HyperLogLog::HEAP_BYTES);
+    }
+
+    #[test]
+    fn bits_per_item_tracks_utilization() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        let expected = -f64::ln(0.01) / f64::ln(2.0).powi(2);
+        assert!((bloom_filter.bits_per_item() - expected).abs() < 0.01);
+        assert_eq!(bloom_filter.actual_bits_per_item(), f64::INFINITY);
+
+        for i in 0..500 {
+            bloom_filter.add(i);
+        }
+        let half_full = bloom_filter.actual_bits_per_item();
+        assert!(half_full > bloom_filter.bits_per_item());
+
+        for i in 500..2000 {
+            bloom_filter.add(i);
+        }
+        let over_filled = bloom_filter.actual_bits_per_item();
+        assert!(over_filled < half_full);
+        assert!(over_filled < bloom_filter.bits_per_item());
+    }
+
+    #[test]
+    fn remaining_capacity_shrinks_to_zero() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        assert_eq!(bloom_filter.remaining_capacity(), 100);
+
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+        assert!(bloom_filter.remaining_capacity() <= 15);
+
+        for i in 100..200 {
+            bloom_filter.add(i);
+        }
+        assert_eq!(bloom_filter.remaining_capacity(), 0);
+    }
+
+    #[test]
+    fn contains_batch_matches_contains() {
+        let mut bloom_filter = BloomFilter::new(0.1, 50);
+        for i in (0..100).step_by(2) {
+            bloom_filter.add(i);
+        }
+
+        let items: Vec<i32> = (0..100).collect();
+        let expected: Vec<bool> = items.iter().map(|item| bloom_filter.contains(item)).collect();
+        assert_eq!(bloom_filter.contains_batch(&items), expected);
+        assert!(bloom_filter.contains_batch::<i32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn explicit_aliases_agree_with_contains() {
+        let mut bloom_filter = BloomFilter::new(0.5, 10);
+        for i in 0..10 {
+            bloom_filter.add(i);
+        }
+
+        for i in 0..100 {
+            assert_eq!(bloom_filter.is_probably_present(i), bloom_filter.contains(i));
+            assert_eq!(bloom_filter.is_definitely_absent(i), !bloom_filter.contains(i));
+        }
+        assert!(!bloom_filter.is_definitely_absent(3));
+    }
+
+    #[test]
+    fn add_by_reference_keeps_ownership() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        let key: Vec<u8> = vec![1, 2, 3];
+        bloom_filter.add_ref(&key);
+
+        assert!(bloom_filter.contains_ref(&key));
+        assert!(bloom_filter.contains(key.clone()));
+        assert!(bloom_filter.contains_ref(&key[..]));
+        assert!(!bloom_filter.contains_ref(&vec![4u8, 5]));
+        assert_eq!(key.len(), 3);
+    }
+
+    #[test]
+    fn hash_histogram_is_flat_for_sequential_integers() {
+        let bloom_filter = BloomFilter::new(0.01, 100);
+        let histogram = bloom_filter.hash_histogram(0..10_000u64, 10);
+
+        assert_eq!(histogram.iter().sum::<usize>(), 10_000);
+        for count in histogram {
+            assert!((800..=1200).contains(&count), "skewed bucket with {} items", count);
+        }
+        assert!(bloom_filter.hash_histogram(0..10u64, 0).is_empty());
+    }
+
+    #[test]
+    fn hash_quality_score_flags_colliding_keys() {
+        struct Colliding(u32);
+        impl Hash for Colliding {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u32(self.0 % 2);
+            }
+        }
+
+        let bloom_filter = BloomFilter::new(0.01, 1000);
+        let good = bloom_filter.hash_quality_score((0..1000).map(|i| format!("item {}", i)));
+        assert!((0.95..=1.05).contains(&good), "score {}", good);
+        let bad = bloom_filter.hash_quality_score((0..1000).map(Colliding));
+        assert!(bad < 0.1, "score {}", bad);
+        assert_eq!(bloom_filter.hash_quality_score(Vec::<u32>::new()), 1.0);
+    }
+
+    #[test]
+    fn over_filled_filter_has_false_positives() {
+        let mut bloom_filter = BloomFilter::new(0.01, 10);
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+
+        let false_positives = bloom_filter.find_false_positives(1000..2000);
+        assert!(!false_positives.is_empty());
+        assert!(false_positives.iter().all(|&candidate| (1000..2000).contains(&candidate) && bloom_filter.contains(candidate)));
+        assert!(BloomFilter::new(0.01, 10).find_false_positives(0..100).is_empty());
+    }
+
+    #[test]
+    fn present_fraction_matches_inserted_share() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        for i in 0..250 {
+            bloom_filter.add(i);
+        }
+
+        let fraction = bloom_filter.approx_present_fraction(0..1000);
+        assert!((0.25..=0.27).contains(&fraction), "fraction {}", fraction);
+        assert_eq!(bloom_filter.approx_present_fraction(0..0), 0.0);
+    }
+
+    #[test]
+    fn bit_entropy_peaks_at_half_fill() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        assert_eq!(bloom_filter.bit_entropy(), 0.0);
+        bloom_filter.add("cat");
+        assert!(bloom_filter.bit_entropy() < 0.05);
+
+        for i in 0..1000 {
+            bloom_filter.add(i);
+        }
+        assert!(bloom_filter.bit_entropy() > 0.99, "entropy {}", bloom_filter.bit_entropy());
+    }
+
+    #[test]
+    fn random_fill_reaches_requested_density() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        bloom_filter.fill_random_to_density(0.3, 42);
+        assert!((bloom_filter.fill_ratio() - 0.3).abs() < 0.001);
+
+        let mut same_seed = BloomFilter::new(0.01, 1000);
+        same_seed.fill_random_to_density(0.3, 42);
+        assert_eq!(same_seed.bitvec, bloom_filter.bitvec);
+
+        bloom_filter.fill_random_to_density(0.1, 7);
+        assert!((bloom_filter.fill_ratio() - 0.3).abs() < 0.001);
+        bloom_filter.fill_random_to_density(1.0, 7);
+        assert_eq!(bloom_filter.count_set_bits(), bloom_filter.vector_len);
+    }
+
+    #[test]
+    fn visitors_see_the_hash_positions() {
+        let bloom_filter = BloomFilter::new(0.01, 100);
+        let mut positions = Vec::new();
+        bloom_filter.add_visit("cat", |index| positions.push(index));
+        assert_eq!(positions, bloom_filter.hash_positions("cat"));
+
+        let mut external = vec![false; bloom_filter.vector_len];
+        for &index in &positions {
+            external[index] = true;
+        }
+        assert!(bloom_filter.contains_visit("cat", |index| external[index]));
+
+        let mut visited = 0;
+        assert!(!bloom_filter.contains_visit("cat", |_| { visited += 1; false }));
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn density_map_shows_clustered_inserts() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        // With a zero second hash every probe of an item lands on h1 % vector_len
+        for i in 0..200 {
+            bloom_filter.add_hashes((i, 0));
+        }
+
+        let density = bloom_filter.density_map(10);
+        assert_eq!(density.len(), 10);
+        assert!(density[0] > 0.1);
+        assert!(density[1..].iter().all(|&region| region == 0.0));
+        assert!(bloom_filter.density_map(0).is_empty());
+    }
+
+    #[test]
+    fn optimal_hashes_shrink_as_filter_fills() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        let empty_optimum = bloom_filter.optimal_hashes_for_current_fill();
+
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+        let full_optimum = bloom_filter.optimal_hashes_for_current_fill();
+        assert!(full_optimum.abs_diff(bloom_filter.num_hashers) <= 1);
+
+        for i in 100..1000 {
+            bloom_filter.add(i);
+        }
+        let over_filled_optimum = bloom_filter.optimal_hashes_for_current_fill();
+        assert!(empty_optimum > full_optimum);
+        assert!(full_optimum > over_filled_optimum);
+        assert_eq!(over_filled_optimum, 1);
+    }
+
+    #[test]
+    fn add_byte_slices_in_bulk() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        let keys: Vec<&[u8]> = vec![b"cat", b"dog", b"", &[0, 1, 2, 255]];
+        bloom_filter.add_byte_slices(keys.iter().copied());
+
+        for key in &keys {
+            assert!(bloom_filter.contains_bytes(key));
+        }
+        assert!(!bloom_filter.contains_bytes(b"monkey"));
+        assert_eq!(bloom_filter.inserted(), keys.len());
+
+        bloom_filter.add_bytes(b"fox");
+        assert!(bloom_filter.contains_bytes(b"fox"));
+
+        let mut strict = BloomFilter::builder(0.01, 3).strict(true).build().unwrap();
+        let overflow = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| strict.add_byte_slices(keys.iter().copied())));
+        assert!(overflow.is_err());
+        assert_eq!(strict.inserted(), 0);
+        assert_eq!(strict.count_set_bits(), 0);
+
+        // Without the empty key, the other three just fit
+        let mut rejecting = BloomFilter::builder(0.01, 3).strict(true).reject_empty(true).build().unwrap();
+        rejecting.add_byte_slices(keys.iter().copied());
+        assert_eq!(rejecting.inserted(), 3);
+    }
+
+    #[test]
+    fn owned_and_borrowed_cows_agree() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        bloom_filter.add_cow(Cow::Owned(b"cat".to_vec()));
+        bloom_filter.add_cow(Cow::Borrowed(b"dog"));
+
+        for key in [b"cat", b"dog"] {
+            assert!(bloom_filter.contains_cow(&Cow::Owned(key.to_vec())));
+            assert!(bloom_filter.contains_cow(&Cow::Borrowed(key)));
+            assert!(bloom_filter.contains_bytes(key));
+        }
+        assert!(!bloom_filter.contains_cow(&Cow::Borrowed(b"fox")));
+    }
+
+    #[test]
+    fn prefixes_of_added_keys_are_found() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        bloom_filter.add_all_prefixes("hello");
+        bloom_filter.add_all_prefixes(b"help");
+        assert_eq!(bloom_filter.inserted(), 9);
+
+        for prefix in ["h", "hel", "hello", "help"] {
+            assert!(bloom_filter.contains_prefix(prefix));
+        }
+        assert!(!bloom_filter.contains_prefix("xyz"));
+        assert!(!bloom_filter.contains_prefix("hello world"));
+    }
+
+    #[test]
+    fn majority_contains_counts_replicas() {
+        let empty = BloomFilter::new(0.01, 100);
+        let mut replicas = vec![empty.clone(); 3];
+        replicas[0].add("cat");
+        replicas[1].add("cat");
+        replicas[2].add("dog");
+
+        let replicas: Vec<&BloomFilter> = replicas.iter().collect();
+        assert_eq!(BloomFilter::majority_contains(&replicas, "cat", 2), Ok(true));
+        assert_eq!(BloomFilter::majority_contains(&replicas, "cat", 3), Ok(false));
+
+        let other = BloomFilter::new(0.01, 100);
+        let mixed = [replicas[0], &other];
+        assert!(matches!(BloomFilter::majority_contains(&mixed, "cat", 1), Err(BloomFilterError::IncompatibleFilters(_))));
+    }
+
+    #[test]
+    fn add_sets_exactly_hash_positions() {
+        for index_strategy in [IndexStrategy::Modulo, IndexStrategy::FastRange, IndexStrategy::EnhancedDoubleHash] {
+            let mut bloom_filter = BloomFilter::builder(0.01, 100).index_strategy(index_strategy).build().unwrap();
+            let mut positions = bloom_filter.hash_positions("cat");
+            assert_eq!(positions.len(), bloom_filter.num_hashers);
+
+            bloom_filter.add("cat");
+            positions.sort_unstable();
+            positions.dedup();
+            let set_bits: Vec<usize> = (0..bloom_filter.vector_len).filter(|&index| bloom_filter.get_bit(index)).collect();
+            assert_eq!(set_bits, positions);
+        }
+    }
+
+    #[test]
+    fn incompatibility_names_every_differing_field() {
+        let base = BloomFilter::with_dimensions(0.01, 100, 1024, 7, 1);
+        assert_eq!(base.explain_incompatibility(&base.clone()), None);
+
+        let cases = [
+            (BloomFilter::with_dimensions(0.01, 100, 512, 7, 1), "vector_len differ: 1024 vs 512"),
+            (BloomFilter::with_dimensions(0.01, 100, 1024, 5, 1), "num_hashers differ: 7 vs 5"),
+            (BloomFilter::with_dimensions(0.01, 100, 1024, 7, 2), "seed differs: 1 vs 2"),
+        ];
+        for (other, message) in cases {
+            assert_eq!(base.explain_incompatibility(&other).unwrap(), message);
+        }
+        let mut fast_range = base.clone();
+        fast_range.index_strategy = IndexStrategy::FastRange;
+        assert_eq!(base.explain_incompatibility(&fast_range).unwrap(), "index_strategy differs: Modulo vs FastRange");
+
+        let mut everything = BloomFilter::with_dimensions(0.01, 100, 512, 5, 2);
+        everything.index_strategy = IndexStrategy::FastRange;
+        let err = everything.clone().union_with(&base).unwrap_err();
+        assert_eq!(err.to_string(), "incompatible filters: vector_len differ: 512 vs 1024; num_hashers differ: 5 vs 7; \
+            seed differs: 2 vs 1; index_strategy differs: FastRange vs Modulo");
+    }
+
+    #[test]
+    fn intersection_set_bits_matches_built_intersection() {
+        let mut a = BloomFilter::new_with_key(0.01, 1000, b"key");
+        let mut b = BloomFilter::new_with_key(0.01, 1000, b"key");
+        for i in 0..600 {
+            a.add(i);
+            b.add(i + 300);
+        }
+
+        let mut intersection = a.clone();
+        for (word, other_word) in intersection.bitvec.iter_mut().zip(&b.bitvec) {
+            *word &= *other_word;
+        }
+        assert_eq!(a.intersection_set_bits(&b).unwrap(), intersection.count_set_bits());
+        assert_eq!(a.intersection_set_bits(&a).unwrap(), a.count_set_bits());
+        assert!(a.intersection_set_bits(&BloomFilter::new(0.01, 10)).is_err());
+    }
+
+    #[test]
+    fn sparse_form_roundtrips_membership() {
+        for index_strategy in [IndexStrategy::Modulo, IndexStrategy::FastRange, IndexStrategy::EnhancedDoubleHash] {
+            let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+            bloom_filter.index_strategy = index_strategy;
+            for i in 0..10 {
+                bloom_filter.add(i);
+            }
+            let (vector_len, num_hashers, seed) = (bloom_filter.vector_len, bloom_filter.num_hashers, bloom_filter.seed);
+
+            let positions = bloom_filter.clone().into_sparse().unwrap();
+            assert_eq!(positions.len(), bloom_filter.count_set_bits());
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+            let rebuilt = BloomFilter::from_sparse(vector_len, num_hashers, seed, index_strategy, &positions).unwrap();
+            assert_eq!(rebuilt.index_strategy, index_strategy);
+            assert_eq!(rebuilt.bitvec, bloom_filter.bitvec);
+            assert!((0..10).all(|i| rebuilt.contains(i)), "{:?}", index_strategy);
+            assert!((0..1000).all(|i| rebuilt.contains(i) == bloom_filter.contains(i)));
+            assert!(rebuilt.inserted().abs_diff(10) <= 1);
+        }
+
+        let (vector_len, num_hashers) = (1000, 3);
+        assert!(matches!(
+            BloomFilter::from_sparse(vector_len, num_hashers, 42, IndexStrategy::default(), &[vector_len as u32]),
+            Err(BloomFilterError::InvalidFormat(_))
+        ));
+        assert_eq!(
+            BloomFilter::from_sparse(0, num_hashers, 42, IndexStrategy::default(), &[]).unwrap_err(),
+            BloomFilterError::InvalidDimensions { vector_len: 0, num_hashers }
+        );
+
+        // Only the claimed length matters, so no 512 MiB of bits are needed to hit the limit
+        let mut huge = BloomFilter::with_dimensions(0.01, 100, 1000, 3, 42);
+        huge.vector_len = (1 << 32) + 1;
+        assert_eq!(huge.into_sparse().unwrap_err(), BloomFilterError::FilterTooLarge(100));
+    }
+
+    #[test]
+    fn from_sparse_rejects_hostile_input() {
+        assert!(matches!(
+            BloomFilter::from_sparse(usize::MAX, 3, 42, IndexStrategy::default(), &[]),
+            Err(BloomFilterError::FilterTooLarge(_))
+        ));
+        assert!(matches!(
+            BloomFilter::from_sparse((1 << 32) + 1, 3, 42, IndexStrategy::default(), &[]),
+            Err(BloomFilterError::FilterTooLarge(_))
+        ));
+
+        // Every bit set estimates infinitely many items; the count must stay addable
+        let positions: Vec<u32> = (0..64).collect();
+        let mut saturated = BloomFilter::from_sparse(64, 3, 42, IndexStrategy::default(), &positions).unwrap();
+        assert_eq!(saturated.estimate_cardinality(), usize::MAX);
+        assert_eq!(saturated.inserted(), 64);
+        saturated.add("cat");
+        assert_eq!(saturated.inserted(), 65);
+    }
+
+    #[test]
+    fn restore_drops_inserts_made_after_the_snapshot() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        bloom_filter.add("cat");
+        let snapshot = bloom_filter.snapshot();
+
+        bloom_filter.add("dog");
+        assert!(bloom_filter.contains("dog"));
+        bloom_filter.restore(snapshot.clone()).unwrap();
+        assert!(bloom_filter.contains("cat"));
+        assert!(!bloom_filter.contains("dog"));
+        assert_eq!(bloom_filter.inserted(), 1);
+
+        let mut other = BloomFilter::new_with_key(0.01, 100, b"other key");
+        assert!(matches!(other.restore(snapshot), Err(BloomFilterError::IncompatibleFilters(_))));
+        assert_eq!(other.seed(), key_seed(b"other key"));
+    }
+
+    #[test]
+    fn reseeding_rebuilds_from_items_under_the_new_seed() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        assert!(bloom_filter.can_reseed_losslessly());
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+        assert!(!bloom_filter.can_reseed_losslessly());
+
+        let reseeded = bloom_filter.reseed_with_items(42, 0..100).unwrap();
+        assert_eq!(reseeded.seed(), 42);
+        assert_eq!(reseeded.vector_len(), bloom_filter.vector_len());
+        assert!((0..100).all(|i| reseeded.contains(i)));
+        assert_ne!(reseeded.bitvec, bloom_filter.bitvec);
+    }
+
+    #[test]
+    fn reseeding_keeps_every_setting() {
+        let strict = BloomFilter::builder(0.01, 10).strict(true).fp_tolerance(0.25).branchless_contains(true).build().unwrap();
+        let reseeded = strict.reseed_with_items(42, 0..10).unwrap();
+        assert!(reseeded.strict && reseeded.branchless);
+        assert_eq!(reseeded.fp_tolerance, 0.25);
+        assert_eq!(strict.reseed_with_items(42, 0..11).unwrap_err(), BloomFilterError::CapacityExceeded(10));
+    }
+
+    #[test]
+    fn symmetric_difference_reflects_changed_items() {
+        let mut before = BloomFilter::new_with_key(0.01, 1000, b"key");
+        for i in 0..500 {
+            before.add(i);
+        }
+        let mut after = before.clone();
+        for i in 500..505 {
+            after.add(i);
+        }
+
+        let xor = before.symmetric_difference_bits(&after).unwrap();
+        let changed_bits: usize = xor.iter().map(|word| word.count_ones() as usize).sum();
+        assert!(changed_bits > 0 && changed_bits <= 5 * before.num_hashers, "changed bits {}", changed_bits);
+        let estimate = before.symmetric_difference_cardinality(&after).unwrap();
+        assert!((3..=8).contains(&estimate), "estimate {}", estimate);
+
+        assert_eq!(before.symmetric_difference_cardinality(&before).unwrap(), 0);
+        assert!(before.symmetric_difference_bits(&BloomFilter::new(0.01, 10)).is_err());
+    }
+
+    #[test]
+    fn difference_cardinality_counts_items_only_in_self() {
+        let mut a = BloomFilter::new_with_key(0.01, 2000, b"key");
+        let mut b = BloomFilter::new_with_key(0.01, 2000, b"key");
+        for i in 0..1000 {
+            a.add(i);
+        }
+        for i in 500..1500 {
+            b.add(i);
+        }
+
+        let intersection = a.estimate_intersection_cardinality(&b).unwrap();
+        assert!(intersection.abs_diff(500) <= 50, "intersection estimate {}", intersection);
+        let difference = a.estimate_difference_cardinality(&b).unwrap();
+        assert!(difference.abs_diff(500) <= 50, "difference estimate {}", difference);
+        assert_eq!(a.estimate_difference_cardinality(&a).unwrap(), 0);
+
+        let other_seed = BloomFilter::new_with_key(0.01, 2000, b"other key");
+        assert!(matches!(a.estimate_difference_cardinality(&other_seed), Err(BloomFilterError::IncompatibleFilters(_))));
+    }
+
+    #[test]
+    fn compact_trims_storage_to_vector_len() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        bloom_filter.add("cat");
+        let words = BloomFilter::words_for(bloom_filter.vector_len);
+        bloom_filter.bitvec.extend([u64::MAX; 3]);
+        bloom_filter.bitvec.reserve(100);
+
+        bloom_filter.compact();
+        assert_eq!(bloom_filter.bitvec.len(), words);
+        assert_eq!(bloom_filter.bitvec.capacity(), words);
+        assert!(bloom_filter.contains("cat"));
+        assert!(!bloom_filter.contains("dog"));
+    }
+
+    #[test]
+    fn combine_all_projects_onto_the_largest_filter() {
+        for index_strategy in [IndexStrategy::Modulo, IndexStrategy::FastRange] {
+            let mut filters: Vec<BloomFilter> = [256, 512, 1024]
+                .iter()
+                .map(|&vector_len| {
+                    let mut filter = BloomFilter::with_dimensions(0.01, 30, vector_len, 5, 7);
+                    filter.index_strategy = index_strategy;
+                    filter
+                })
+                .collect();
+            for (offset, filter) in filters.iter_mut().enumerate() {
+                for i in 0..20 {
+                    filter.add(offset * 1000 + i);
+                }
+            }
+
+            let combined = BloomFilter::combine_all(filters).unwrap();
+            assert_eq!(combined.vector_len, 1024);
+            assert!((0..3usize).all(|offset| (0..20).all(|i| combined.contains(offset * 1000 + i))));
+        }
+
+        assert_eq!(BloomFilter::combine_all(Vec::new()).unwrap_err(), BloomFilterError::NoFilters);
+        let odd = vec![BloomFilter::with_dimensions(0.01, 30, 256, 5, 7), BloomFilter::with_dimensions(0.01, 30, 1000, 5, 7)];
+        assert_eq!(BloomFilter::combine_all(odd).unwrap_err(), BloomFilterError::NotPowerOfTwo(1000));
+    }
+
+    #[test]
+    fn lossy_union_folds_the_larger_filter() {
+        let mut small = BloomFilter::with_dimensions(0.01, 50, 512, 5, 7);
+        let mut large = BloomFilter::with_dimensions(0.01, 100, 1024, 5, 7);
+        for i in 0..20 {
+            small.add(i);
+            large.add(i + 1000);
+        }
+
+        for union in [small.lossy_union(&large).unwrap(), large.lossy_union(&small).unwrap()] {
+            assert_eq!(union.vector_len, 512);
+            assert!((0..20).all(|i| union.contains(i) && union.contains(i + 1000)));
+        }
+
+        let odd = BloomFilter::with_dimensions(0.01, 100, 1000, 5, 7);
+        assert_eq!(small.lossy_union(&odd).unwrap_err(), BloomFilterError::NotPowerOfTwo(1000));
+        let other_seed = BloomFilter::with_dimensions(0.01, 100, 1024, 5, 8);
+        assert!(matches!(small.lossy_union(&other_seed), Err(BloomFilterError::IncompatibleFilters(_))));
+    }
+
+    #[test]
+    fn bit_diff_finds_positions_of_extra_item() {
+        let mut first = BloomFilter::new(0.01, 100);
+        for i in 0..20 {
+            first.add(i);
+        }
+        let mut second = first.clone();
+        assert_eq!(first.bit_diff(&second), Ok(vec![]));
+
+        second.add("extra");
+        let extra_indexes: Vec<usize> = second.indexes("extra").collect();
+        let diff = first.bit_diff(&second).unwrap();
+        assert!(!diff.is_empty() && diff.len() <= second.num_hashers);
+        assert!(diff.iter().all(|index| extra_indexes.contains(index)));
+        assert!(diff.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(first.bit_diff_count(&second), Ok(diff.len()));
+
+        assert!(first.bit_diff(&BloomFilter::new(0.01, 100)).is_err());
+    }
+
+    #[test]
+    fn merge_items_reports_over_capacity() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        let report = bloom_filter.merge_items(0..50).unwrap();
+        assert_eq!(report.inserted_count, 50);
+        assert!(!report.needs_rebuild);
+
+        let report = bloom_filter.merge_items(50..300).unwrap();
+        assert_eq!(report.inserted_count, 250);
+        assert!(report.needs_rebuild);
+        assert_eq!(report.current_fp_rate, bloom_filter.current_false_positive_rate());
+        assert!(report.current_fp_rate > 0.01);
+    }
+
+    #[test]
+    fn strict_merge_items_is_all_or_nothing() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 10).strict(true).build().unwrap();
+        assert_eq!(bloom_filter.merge_items(0..6).unwrap().inserted_count, 6);
+
+        let set_bits = bloom_filter.count_set_bits();
+        assert_eq!(bloom_filter.merge_items(6..11).unwrap_err(), BloomFilterError::CapacityExceeded(10));
+        assert_eq!(bloom_filter.inserted(), 6);
+        assert_eq!(bloom_filter.count_set_bits(), set_bits);
+
+        assert_eq!(bloom_filter.merge_items(6..10).unwrap().inserted_count, 4);
+
+        let mut rejecting = BloomFilter::builder(0.01, 2).strict(true).reject_empty(true).build().unwrap();
+        let report = rejecting.merge_items(["", "cat", "", "dog"]).unwrap();
+        assert_eq!(report.inserted_count, 2);
+        assert_eq!(rejecting.inserted(), 2);
+    }
+
+    #[test]
+    fn resize_rebuild_restores_target_rate() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        for i in 0..300 {
+            bloom_filter.add(i);
+        }
+        assert!(bloom_filter.needs_rebuild());
+
+        let rebuilt = bloom_filter.resize_rebuild(0..300).unwrap();
+        assert!(rebuilt.vector_len > bloom_filter.vector_len);
+        assert!(bloom_filter.current_false_positive_rate() > 0.3);
+        assert!(rebuilt.current_false_positive_rate() < 0.012, "fp rate {}", rebuilt.current_false_positive_rate());
+        assert!((0..300).all(|i| rebuilt.contains(i)));
+    }
+
+    #[test]
+    fn resize_rebuild_handles_saturation_and_keeps_settings() {
+        let mut saturated = BloomFilter::builder(0.5, 1).reject_empty(true).branchless_contains(true).fp_tolerance(0.5).build().unwrap();
+        for i in 0..50 {
+            saturated.add(i);
+        }
+        let rebuilt = saturated.resize_rebuild(0..50).unwrap();
+        assert_eq!((rebuilt.vector_len, rebuilt.num_hashers), BloomFilter::optimal_params(0.5, 50).unwrap());
+        assert!((0..50).all(|i| rebuilt.contains(i)));
+        assert_eq!(rebuilt.seed, saturated.seed);
+        assert!(rebuilt.reject_empty && rebuilt.branchless && !rebuilt.strict);
+        assert_eq!(rebuilt.fp_tolerance, 0.5);
+
+        let mut strict = BloomFilter::builder(0.01, 10).strict(true).build().unwrap();
+        for i in 0..10 {
+            strict.add(i);
+        }
+        assert!(matches!(strict.resize_rebuild(0..20), Err(BloomFilterError::CapacityExceeded(_))));
+        assert!(strict.resize_rebuild(0..10).unwrap().strict);
+    }
+
+    #[test]
+    fn merge_or_rebuild_unions_or_rebuilds() {
+        let empty = BloomFilter::new(0.01, 100);
+        let mut first = empty.clone();
+        let mut second = empty.clone();
+        first.add("cat");
+        second.add("dog");
+
+        let union = first.merge_or_rebuild(&second, Vec::<&str>::new()).unwrap();
+        assert!(union.is_compatible_with(&first));
+        assert!(union.contains("cat") && union.contains("dog"));
+
+        let mut incompatible = BloomFilter::new(0.01, 50);
+        incompatible.add("dog");
+        let rebuilt = first.merge_or_rebuild(&incompatible, ["cat", "dog", "fox"]).unwrap();
+        assert!(!rebuilt.is_compatible_with(&first));
+        assert_eq!(rebuilt.data_set_size, 3);
+        assert!(rebuilt.contains("cat") && rebuilt.contains("dog") && rebuilt.contains("fox"));
+        assert_eq!(first.merge_or_rebuild(&incompatible, Vec::<&str>::new()).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+    }
+
+    #[test]
+    fn merge_or_rebuild_keeps_every_setting() {
+        let first = BloomFilter::builder(0.01, 100)
+            .strict(true)
+            .index_strategy(IndexStrategy::FastRange)
+            .fp_tolerance(0.25)
+            .reject_empty(true)
+            .branchless_contains(true)
+            .build()
+            .unwrap();
+        let incompatible = BloomFilter::new(0.01, 50);
+
+        let rebuilt = first.merge_or_rebuild(&incompatible, ["cat", "", "dog"]).unwrap();
+        assert_eq!(rebuilt.seed, first.seed);
+        assert_eq!(rebuilt.index_strategy, IndexStrategy::FastRange);
+        assert!(rebuilt.strict && rebuilt.reject_empty && rebuilt.branchless);
+        assert_eq!(rebuilt.fp_tolerance, 0.25);
+        assert_eq!(rebuilt.inserted(), 2);
+        assert!(rebuilt.contains("cat") && rebuilt.contains("dog") && !rebuilt.contains(""));
+    }
+
+    #[test]
+    fn which_contain_returns_matching_labels() {
+        let mut tenant_a = BloomFilter::new(0.01, 100);
+        let mut tenant_b = BloomFilter::new(0.01, 100);
+        let mut tenant_c = BloomFilter::new(0.001, 50);
+        tenant_a.add("key");
+        tenant_b.add("other");
+        tenant_c.add("key");
+
+        let filters = [("a", &tenant_a), ("b", &tenant_b), ("c", &tenant_c)];
+        assert_eq!(BloomFilter::which_contain(&filters, "key"), vec!["a", "c"]);
+        assert!(BloomFilter::which_contain(&filters, "missing").is_empty());
+    }
+
+    #[test]
+    fn fold_halve_keeps_every_item() {
+        let mut bloom_filter = BloomFilter::new(0.05, 82);
+        assert_eq!(bloom_filter.vector_len, 512);
+        for i in 0..82 {
+            bloom_filter.add(i);
+        }
+
+        let folded = bloom_filter.fold_halve().unwrap();
+        assert_eq!(folded.vector_len, 256);
+        for i in 0..82 {
+            assert!(folded.contains(i));
+        }
+
+        let mut fast_range = BloomFilter::builder(0.05, 82).index_strategy(IndexStrategy::FastRange).build().unwrap();
+        for i in 0..82 {
+            fast_range.add(i);
+        }
+        let folded = fast_range.fold_halve().unwrap();
+        for i in 0..82 {
+            assert!(folded.contains(i));
+        }
+
+        let not_power_of_two = BloomFilter::new(0.01, 100);
+        assert_eq!(not_power_of_two.fold_halve().unwrap_err(), BloomFilterError::NotPowerOfTwo(not_power_of_two.vector_len));
+    }
+
+    #[test]
+    fn index_strategy_is_part_of_compatibility() {
+        let modulo = BloomFilter::new(0.01, 100);
+        let mut fast_range = modulo.clone();
+        fast_range.index_strategy = IndexStrategy::FastRange;
+        fast_range.add("cat");
+        assert!(fast_range.contains("cat"));
+
+        assert!(!modulo.is_compatible_with(&fast_range));
+        let mut union = modulo.clone();
+        match union.union_with(&fast_range) {
+            Err(BloomFilterError::IncompatibleFilters(reason)) => assert!(reason.contains("index_strategy")),
+            other => panic!("expected incompatible filters error, got {:?}", other),
+        }
+        assert!(BloomFilter::union_all([&modulo, &fast_range]).is_err());
+    }
+
+    #[test]
+    fn enhanced_double_hashing_is_no_worse() {
+        let false_positive_rate = |index_strategy| {
+            let mut bloom_filter = BloomFilter::new_with_key(0.000001, 1000, b"enhanced");
+            bloom_filter.index_strategy = index_strategy;
+            assert_eq!(bloom_filter.num_hashers, 20);
+            for i in 0..3000u64 {
+                bloom_filter.add(i);
+            }
+            (3000..103_000u64).filter(|&i| bloom_filter.contains(i)).count() as f64 / 100_000.0
+        };
+
+        let plain = false_positive_rate(IndexStrategy::Modulo);
+        let enhanced = false_positive_rate(IndexStrategy::EnhancedDoubleHash);
+        assert!(plain > 0.0);
+        assert!(enhanced <= plain * 1.1, "enhanced {} vs plain {}", enhanced, plain);
+    }
+
+    #[test]
+    fn clear_range_clears_only_the_sub_range() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        assert!(bloom_filter.vector_len > 300);
+        for index in 10..300 {
+            bloom_filter.set_bit(index);
+        }
+
+        bloom_filter.clear_range(50, 200);
+        for index in 0..bloom_filter.vector_len {
+            let expected = (10..50).contains(&index) || (200..300).contains(&index);
+            assert_eq!(bloom_filter.get_bit(index), expected, "bit {}", index);
+        }
+
+        bloom_filter.clear_range(70, 75);
+        bloom_filter.clear_range(20, 20);
+        assert_eq!(bloom_filter.count_set_bits(), 40 + 100);
+
+        bloom_filter.add("cat");
+        bloom_filter.clear();
+        assert_eq!(bloom_filter.count_set_bits(), 0);
+        assert_eq!(bloom_filter.inserted(), 0);
+        assert!(!bloom_filter.contains("cat"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "strict-debug", debug_assertions))]
+    #[should_panic(expected = "above target")]
+    fn mis_sized_filter_fails_debug_check() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        bloom_filter.num_hashers = 1;
+        for i in 0..1000 {
+            bloom_filter.add(i);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "strict-debug")]
+    fn well_sized_filter_passes_debug_check() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        for i in 0..1000 {
+            bloom_filter.add(i);
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn bloom_filter_is_send_and_sync() {
+        assert_send_sync::<BloomFilter>();
+        assert_send_sync::<BloomFilterBuilder>();
+        assert_send_sync::<BloomFilterError>();
+    }
+
+    #[test]
+    fn confidence_drops_as_filter_fills() {
+        let mut light = BloomFilter::new(0.01, 100);
+        assert_eq!(light.contains_with_confidence("cat"), (false, 1.0));
+        light.add("cat");
+
+        let mut over_filled = light.clone();
+        for i in 0..500 {
+            over_filled.add(i);
+        }
+
+        let (light_present, light_confidence) = light.contains_with_confidence("cat");
+        let (over_filled_present, over_filled_confidence) = over_filled.contains_with_confidence("cat");
+        assert!(light_present && over_filled_present);
+        assert!(over_filled_confidence < light_confidence);
+        assert!(over_filled.current_false_positive_rate() > over_filled.prob_fp);
+    }
+
+}
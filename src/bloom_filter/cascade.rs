@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use super::{random_seed, BloomFilter};
+use crate::{BloomFilterError, BloomHash};
+
+
+/// Levels a cascade may grow to before construction gives up
+const MAX_LEVELS: usize = 64;
+
+/// Filter cascade encoding membership in an included set, exactly for the items of an excluded
+/// set known at construction. \
+/// Level 0 holds the included items, level 1 the excluded items level 0 reports as false
+/// positives, level 2 the included items level 1 reports, and so on until no false positives
+/// are left. An item is included if the first level that misses it is odd, or if every level
+/// reports it and the last level is even.
+///
+/// All levels share one seed, so `contains` hashes an item once and only varies the index
+/// derivation per level.
+#[derive(Clone, Debug)]
+pub struct FilterCascade {
+    levels: Vec<BloomFilter>,
+}
+
+impl FilterCascade {
+
+    /// Builds the cascade, each level sized for its items at prob_fp. \
+    /// The sets must be disjoint: an item in both can never be told apart, so construction fails
+    /// with `OverlappingSets` before building any level. Each level only shrinks the false
+    /// positives by a factor of about prob_fp, so with high rates a cascade can run out of
+    /// levels and fail with `TooManyLevels`.
+    pub fn build<T: BloomHash>(prob_fp: f64, included: &[T], excluded: &[T]) -> Result<Self, BloomFilterError> {
+        BloomFilter::optimal_params(prob_fp, 1)?;
+        let seed = random_seed();
+        let hasher = BloomFilter::with_seed(prob_fp, 1, seed);
+        let mut insert: Vec<(u64, u64)> = included.iter().map(|item| hasher.hashes_of(item)).collect();
+        let mut check: Vec<(u64, u64)> = excluded.iter().map(|item| hasher.hashes_of(item)).collect();
+
+        // Items are only told apart by their base hashes, so sharing those is sharing the item
+        let included_hashes: HashSet<(u64, u64)> = insert.iter().copied().collect();
+        if check.iter().any(|hashes| included_hashes.contains(hashes)) {
+            return Err(BloomFilterError::OverlappingSets);
+        }
+
+        let mut levels = Vec::new();
+        loop {
+            if levels.len() == MAX_LEVELS {
+                return Err(BloomFilterError::TooManyLevels(MAX_LEVELS));
+            }
+
+            let level_index = levels.len();
+            let mut level = BloomFilter::with_seed(prob_fp, insert.len().max(1), seed);
+            for &hashes in &insert {
+                level.add_hashes(level_hashes(hashes, level_index));
+            }
+            let false_positives: Vec<(u64, u64)> = check
+                .into_iter()
+                .filter(|&hashes| level.contains_hashes(level_hashes(hashes, level_index)))
+                .collect();
+            levels.push(level);
+
+            if false_positives.is_empty() {
+                return Ok(FilterCascade { levels });
+            }
+            check = insert;
+            insert = false_positives;
+        }
+    }
+
+    /// Whether data is in the included set. Exact for the items given to `build`; other items
+    /// are reported included at roughly the rate of level 0's false positives.
+    #[must_use]
+    pub fn contains<T: BloomHash>(&self, data: T) -> bool {
+        let hashes = self.levels[0].compute_hashes(data);
+        for (level_index, level) in self.levels.iter().enumerate() {
+            if !level.contains_hashes(level_hashes(hashes, level_index)) {
+                return level_index % 2 == 1;
+            }
+        }
+        self.levels.len() % 2 == 1
+    }
+
+    /// Number of levels built
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+/// Level-specific base hashes, so that the levels' false positives are independent
+fn level_hashes((hash_1, hash_2): (u64, u64), level_index: usize) -> (u64, u64) {
+    (hash_1 ^ (level_index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15), hash_2)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade_encodes_set_difference_exactly() {
+        let included: Vec<u32> = (0..500).collect();
+        let excluded: Vec<u32> = (500..5000).collect();
+        let cascade = FilterCascade::build(0.1, &included, &excluded).unwrap();
+
+        assert!(cascade.levels() > 1);
+        assert!(included.iter().all(|&item| cascade.contains(item)));
+        assert!(excluded.iter().all(|&item| !cascade.contains(item)));
+    }
+
+    #[test]
+    fn overlapping_sets_are_rejected() {
+        let included = ["cat", "dog"];
+        let excluded = ["dog", "fox"];
+        assert_eq!(FilterCascade::build(0.1, &included, &excluded).unwrap_err(), BloomFilterError::OverlappingSets);
+        assert!(matches!(FilterCascade::build(1.5, &included, &[]), Err(BloomFilterError::InvalidProbability(_))));
+    }
+
+    #[test]
+    fn disjoint_sets_running_out_of_levels_are_not_called_overlapping() {
+        let included: Vec<u32> = (0..5000).collect();
+        let excluded: Vec<u32> = (5000..10_000).collect();
+        assert_eq!(FilterCascade::build(0.99, &included, &excluded).unwrap_err(), BloomFilterError::TooManyLevels(MAX_LEVELS));
+    }
+}
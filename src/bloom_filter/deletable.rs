@@ -0,0 +1,191 @@
+use super::BloomFilter;
+use crate::BloomHash;
+
+
+/// Bloom filter that supports removals, by keeping a counter next to every bit. \
+/// Lookups read the plain bit filter as fast as a [`BloomFilter`]; `add` and `remove` keep the
+/// counters in sync and a bit is only cleared once its counter drops to zero, so removing one
+/// item never clears a bit another item still needs.
+///
+/// Counters are `u8` and saturate at 255 instead of wrapping around to zero; a saturated counter
+/// is never decremented again, so its bit stays set for good.
+///
+/// Only remove items that were actually added: removing a false positive decrements counters
+/// that belong to other items and can make them disappear.
+///
+/// With the `hll` feature, additions also feed the inner filter's distinct counter. A
+/// HyperLogLog can't forget, so removals don't lower
+/// `as_bloom_filter().precise_cardinality()`: it counts the distinct items ever added.
+#[derive(Clone, Debug)]
+pub struct DeletableBloomFilter {
+    bloom_filter: BloomFilter,
+    counters: Vec<u8>,   // one counter per bit
+}
+
+impl DeletableBloomFilter {
+
+    /// Create new deletable bloom filter, see [`BloomFilter::new`]
+    pub fn new(prob_fp: f64, data_set_size: usize) -> Self {
+        let bloom_filter = BloomFilter::new(prob_fp, data_set_size);
+        let counters = vec![0; bloom_filter.vector_len];
+        DeletableBloomFilter { bloom_filter, counters }
+    }
+
+    /// Adds data, incrementing the counter of each of its bits
+    pub fn add<T: BloomHash>(&mut self, data: T) {
+        let (hash_1, hash_2) = self.bloom_filter.compute_hashes(data);
+        self.increment(hash_1, hash_2);
+    }
+
+    /// Adds the raw content of a byte slice, see [`BloomFilter::add_bytes`]
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        let (hash_1, hash_2) = self.bloom_filter.byte_hashes(bytes);
+        self.increment(hash_1, hash_2);
+    }
+
+    fn increment(&mut self, hash_1: u64, hash_2: u64) {
+        let indexes: Vec<usize> = self.bloom_filter.indexes_from_hashes(hash_1, hash_2).collect();
+        for index in indexes {
+            self.counters[index] = self.counters[index].saturating_add(1);
+            self.bloom_filter.set_bit(index);
+        }
+        self.bloom_filter.inserted += 1;
+
+        #[cfg(feature = "hll")]
+        self.bloom_filter.hyperloglog.insert(hash_1);
+    }
+
+    /// Removes one addition of data, returning false (and changing nothing) if data is not present. \
+    /// An item added twice stays present until it is removed twice.
+    pub fn remove<T: BloomHash>(&mut self, data: T) -> bool {
+        let indexes: Vec<usize> = self.bloom_filter.indexes(data).collect();
+        if !indexes.iter().all(|&index| self.bloom_filter.get_bit(index)) {
+            return false;
+        }
+
+        for index in indexes {
+            // A saturated counter no longer knows how many additions it covers, so it stays pinned:
+            // decrementing it could clear a bit that is still needed
+            if self.counters[index] == u8::MAX {
+                continue;
+            }
+            self.counters[index] = self.counters[index].saturating_sub(1);
+            if self.counters[index] == 0 {
+                self.bloom_filter.clear_bit(index);
+            }
+        }
+        self.bloom_filter.inserted = self.bloom_filter.inserted.saturating_sub(1);
+        true
+    }
+
+    /// Removes every item from the caller-supplied source that fails pred, keeping the ones it accepts. \
+    /// A bloom filter can't enumerate its contents, so items must list what was added.
+    pub fn retain<T: BloomHash, F: FnMut(&T) -> bool, I: IntoIterator<Item = T>>(&mut self, items: I, mut pred: F) {
+        for item in items {
+            if !pred(&item) {
+                self.remove(item);
+            }
+        }
+    }
+
+    /// Checks whether data is present, reading only the bit filter. See [`BloomFilter::contains`]
+    #[must_use]
+    pub fn contains<T: BloomHash>(&self, data: T) -> bool {
+        self.bloom_filter.contains(data)
+    }
+
+    /// Checks whether the raw content of a byte slice was added with `add_bytes`
+    #[must_use]
+    pub fn contains_bytes(&self, bytes: &[u8]) -> bool {
+        self.bloom_filter.contains_bytes(bytes)
+    }
+
+    /// The bit filter, e.g. to serialize or union it
+    pub fn as_bloom_filter(&self) -> &BloomFilter {
+        &self.bloom_filter
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_after_repeated_add() {
+        let mut bloom_filter = DeletableBloomFilter::new(0.01, 100);
+        bloom_filter.add("cat");
+        bloom_filter.add("cat");
+        bloom_filter.add("dog");
+
+        assert!(bloom_filter.remove("cat"));
+        assert!(bloom_filter.contains("cat"));
+        assert!(bloom_filter.remove("cat"));
+        assert!(!bloom_filter.contains("cat"));
+        assert!(!bloom_filter.remove("cat"));
+
+        assert!(bloom_filter.contains("dog"));
+        assert_eq!(bloom_filter.as_bloom_filter().inserted(), 1);
+    }
+
+    #[test]
+    fn counters_saturate_instead_of_wrapping() {
+        let mut bloom_filter = DeletableBloomFilter::new(0.01, 100);
+        for _ in 0..300 {
+            bloom_filter.add("cat");
+        }
+        assert!(bloom_filter.contains("cat"));
+        for index in bloom_filter.bloom_filter.hash_positions("cat") {
+            assert_eq!(bloom_filter.counters[index], u8::MAX);
+        }
+
+        for _ in 0..300 {
+            assert!(bloom_filter.remove("cat"));
+        }
+        assert!(bloom_filter.contains("cat"));
+    }
+
+    #[test]
+    fn retain_only_even_items() {
+        let mut bloom_filter = DeletableBloomFilter::new(0.01, 10);
+        for i in 1..=10 {
+            bloom_filter.add(i);
+        }
+
+        bloom_filter.retain(1..=10, |i| i % 2 == 0);
+        for i in (2..=10).step_by(2) {
+            assert!(bloom_filter.contains(i));
+        }
+        let odds_left = (1..=10).step_by(2).filter(|&i| bloom_filter.contains(i)).count();
+        assert!(odds_left <= 1);
+        assert_eq!(bloom_filter.as_bloom_filter().inserted(), 5);
+    }
+
+    #[test]
+    fn removals_keep_bits_of_other_items() {
+        let mut bloom_filter = DeletableBloomFilter::new(0.01, 100);
+        for i in 0..100 {
+            bloom_filter.add(i);
+        }
+        for i in (0..100).step_by(2) {
+            assert!(bloom_filter.remove(i));
+        }
+        for i in (1..100).step_by(2) {
+            assert!(bloom_filter.contains(i));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "hll")]
+    fn additions_feed_the_distinct_counter() {
+        let mut bloom_filter = DeletableBloomFilter::new(0.01, 1000);
+        for i in 0..500 {
+            bloom_filter.add(i);
+        }
+        bloom_filter.add_bytes(b"cat");
+        bloom_filter.remove(0);
+
+        let precise = bloom_filter.as_bloom_filter().precise_cardinality();
+        assert!(precise.abs_diff(501) <= 20, "precise cardinality {}", precise);
+    }
+}
@@ -0,0 +1,746 @@
+use std::io::{self, Read, Write};
+
+use super::{BloomFilter, DEFAULT_FP_TOLERANCE, MAX_VECTOR_LEN};
+use crate::{BloomFilterError, FilterConfig, IndexStrategy};
+
+
+/// Every change to the wire layout bumps the version, older versions stay readable:
+/// - 1: `V1_HEADER_LEN` header without an index strategy, then one byte (0 or 1) per bit
+/// - 2: `HEADER_LEN` header adding the index strategy, fp_tolerance and the flags byte, then the
+///   bits packed into u64 words
+const FORMAT_VERSION: u8 = 2;
+
+/// version, prob_fp, data_set_size, vector_len, num_hashers, seed, inserted, strict
+const V1_HEADER_LEN: usize = 1 + 8 * 6 + 1;
+
+/// The version 1 header, then index_strategy, fp_tolerance and the flags byte
+const HEADER_LEN: usize = V1_HEADER_LEN + 1 + 8 + 1;
+
+/// Bits of the flags byte, the rest must be clear
+const FLAG_REJECT_EMPTY: u8 = 1;
+const FLAG_BRANCHLESS: u8 = 1 << 1;
+
+/// Bit words buffered per write or read when streaming
+const CHUNK_WORDS: usize = 4096;
+
+/// Header fields as read, before any storage is allocated for the bits
+struct Header {
+    prob_fp: f64,
+    data_set_size: usize,
+    vector_len: usize,
+    num_hashers: usize,
+    seed: u64,
+    inserted: usize,
+    strict: bool,
+    index_strategy: IndexStrategy,
+    fp_tolerance: f64,
+    reject_empty: bool,
+    branchless: bool,
+}
+
+impl Header {
+
+    fn words(&self) -> usize {
+        BloomFilter::words_for(self.vector_len)
+    }
+
+    /// The filter described by the header, holding bitvec
+    fn into_filter(self, bitvec: Vec<u64>) -> BloomFilter {
+        let mut bloom_filter = BloomFilter::with_bits(self.prob_fp, self.data_set_size, self.vector_len, self.num_hashers, self.seed, bitvec);
+        bloom_filter.index_strategy = self.index_strategy;
+        bloom_filter.inserted = self.inserted;
+        bloom_filter.strict = self.strict;
+        bloom_filter.fp_tolerance = self.fp_tolerance;
+        bloom_filter.reject_empty = self.reject_empty;
+        bloom_filter.branchless = self.branchless;
+        bloom_filter
+    }
+}
+
+/// Reads little-endian fields from the front of a byte slice
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BloomFilterError> {
+        if self.bytes.len() < len {
+            return Err(BloomFilterError::InvalidFormat(String::from("unexpected end of data")));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BloomFilterError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u64(&mut self) -> Result<u64, BloomFilterError> {
+        let mut word = [0; 8];
+        word.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(word))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, BloomFilterError> {
+        usize::try_from(self.read_u64()?)
+            .map_err(|_| BloomFilterError::InvalidFormat(String::from("size does not fit in usize")))
+    }
+
+    /// Reads `words` little-endian u64 words. The length is checked before allocating, so a
+    /// header claiming a huge filter fails on the short input instead of exhausting memory.
+    fn read_words(&mut self, words: usize) -> Result<Vec<u64>, BloomFilterError> {
+        if self.bytes.len() / 8 < words {
+            return Err(BloomFilterError::InvalidFormat(String::from("unexpected end of data")));
+        }
+        (0..words).map(|_| self.read_u64()).collect()
+    }
+}
+
+
+impl BloomFilter {
+
+    /// Serializes the filter, including its seed, so that `from_bytes` restores an identical filter. \
+    /// Layout: a version byte, the little-endian header fields
+    /// (prob_fp, data_set_size, vector_len, num_hashers, seed, inserted, strict, index_strategy,
+    /// fp_tolerance, then a flags byte with bit 0 for reject_empty and bit 1 for branchless)
+    /// and then the packed bit words, each as a little-endian u64.
+    ///
+    /// The wire format is always little-endian, whatever the host byte order, so a filter saved
+    /// on one platform loads unchanged on any other.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.bitvec.len() * 8);
+        self.write_header(&mut bytes, self.inserted);
+        self.write_bits(&mut bytes);
+        bytes
+    }
+
+    /// Streams the `to_bytes` layout to writer, the header first and then the bit words in
+    /// fixed-size chunks, so the serialized filter is never held in memory as a whole
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        self.write_header(&mut header, self.inserted);
+        writer.write_all(&header)?;
+
+        let mut chunk = Vec::with_capacity(CHUNK_WORDS * 8);
+        for words in self.bitvec.chunks(CHUNK_WORDS) {
+            chunk.clear();
+            for word in words {
+                chunk.extend_from_slice(&word.to_le_bytes());
+            }
+            writer.write_all(&chunk)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a filter written by `write_to` (or `to_bytes`) in the current format version, chunk by
+    /// chunk. Stops right after the bit words, so further data in reader is left unread. \
+    /// Malformed data is reported as an `InvalidData` error wrapping the `BloomFilterError`;
+    /// blobs from older format versions have to go through `from_bytes`.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<BloomFilter> {
+        let invalid_data = |err: BloomFilterError| io::Error::new(io::ErrorKind::InvalidData, err);
+
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let header = Self::read_header(&mut ByteReader { bytes: &header }).map_err(invalid_data)?;
+
+        // The bits grow chunk by chunk as they arrive, so a truncated stream behind a header
+        // claiming a huge filter fails without allocating for the whole claim
+        let mut bitvec = Vec::new();
+        let mut chunk = vec![0; CHUNK_WORDS * 8];
+        let mut remaining = header.words();
+        while remaining > 0 {
+            let words = remaining.min(CHUNK_WORDS);
+            let bytes = &mut chunk[..words * 8];
+            reader.read_exact(bytes)?;
+            bitvec.try_reserve(words).map_err(|_| invalid_data(BloomFilterError::AllocationFailed(header.words().saturating_mul(8))))?;
+            let mut chunk_reader = ByteReader { bytes };
+            for _ in 0..words {
+                bitvec.push(chunk_reader.read_u64().map_err(invalid_data)?);
+            }
+            remaining -= words;
+        }
+
+        let bloom_filter = header.into_filter(bitvec);
+        bloom_filter.validate().map_err(invalid_data)?;
+        Ok(bloom_filter)
+    }
+
+    /// Restores a filter serialized with `to_bytes`. \
+    /// Data written by older versions of the format is migrated to the current representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<BloomFilter, BloomFilterError> {
+        let mut reader = ByteReader { bytes };
+
+        let bloom_filter = match reader.read_u8()? {
+            1 => Self::parse_v1(&mut reader)?,
+            2 => Self::parse_v2(&mut reader)?,
+            version => return Err(BloomFilterError::UnsupportedVersion(version)),
+        };
+        if !reader.bytes.is_empty() {
+            return Err(BloomFilterError::InvalidFormat(String::from("trailing bytes after bit vector")));
+        }
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Version 1: the version 1 header, then one byte (0 or 1) per bit
+    fn parse_v1(reader: &mut ByteReader) -> Result<BloomFilter, BloomFilterError> {
+        let header = Self::read_v1_header_fields(reader)?;
+        let bits = reader.take(header.vector_len)?;   // before allocating for vector_len bits
+        let words = header.words();
+        let mut bloom_filter = header.into_filter(vec![0; words]);
+
+        for (index, &bit) in bits.iter().enumerate() {
+            match bit {
+                0 => {}
+                1 => { bloom_filter.set_bit(index); }
+                _ => return Err(BloomFilterError::InvalidFormat(format!("invalid bit value {}", bit))),
+            }
+        }
+        Ok(bloom_filter)
+    }
+
+    /// Version 2: the header, then the packed bit words
+    fn parse_v2(reader: &mut ByteReader) -> Result<BloomFilter, BloomFilterError> {
+        let header = Self::read_header_fields(reader)?;
+        let bitvec = reader.read_words(header.words())?;
+        let bloom_filter = header.into_filter(bitvec);
+        bloom_filter.check_padding(&bloom_filter.bitvec)?;
+        Ok(bloom_filter)
+    }
+
+    /// Serializes only the configuration (the `to_bytes` header, with no items inserted), so that
+    /// nodes can agree on vector_len, num_hashers, seed and index strategy before exchanging bits
+    /// separately with `bits_as_bytes`.
+    pub fn config_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        self.write_header(&mut bytes, 0);
+        bytes
+    }
+
+    /// Builds an empty filter from a configuration written by `config_to_bytes`. \
+    /// No bits follow to bound the claimed size, so the storage is allocated fallibly and running
+    /// out of memory is reported as `AllocationFailed`.
+    pub fn config_from_bytes(bytes: &[u8]) -> Result<BloomFilter, BloomFilterError> {
+        let mut reader = ByteReader { bytes };
+        let header = Self::read_header(&mut reader)?;
+
+        if !reader.bytes.is_empty() {
+            return Err(BloomFilterError::InvalidFormat(String::from("trailing bytes after configuration")));
+        }
+        let bitvec = Self::try_zeroed_words(header.words())?;
+        let bloom_filter = header.into_filter(bitvec);
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// The packed bit words alone, each as a little-endian u64
+    pub fn bits_as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.bitvec.len() * 8);
+        self.write_bits(&mut bytes);
+        bytes
+    }
+
+    /// Replaces the bits with ones from `bits_as_bytes` of a filter with the same configuration. \
+    /// The `inserted` count is left as is, since it is not part of the bits.
+    pub fn load_bits(&mut self, bytes: &[u8]) -> Result<(), BloomFilterError> {
+        let mut reader = ByteReader { bytes };
+        self.read_bits(&mut reader)?;
+
+        if !reader.bytes.is_empty() {
+            return Err(BloomFilterError::InvalidFormat(String::from("trailing bytes after bit vector")));
+        }
+        Ok(())
+    }
+
+    /// Splits the packed bit words into `parts` roughly equal chunks for storing on different nodes.
+    /// Each part starts with the little-endian u64 index of its first word, followed by its words
+    /// as in `bits_as_bytes`. Together with `config_to_bytes` the parts rebuild the filter via
+    /// `join_storage`; parts can be empty when a filter has fewer words than parts.
+    pub fn split_storage(&self, parts: usize) -> Vec<Vec<u8>> {
+        let words = self.bitvec.len();
+        (0..parts)
+            .map(|part| {
+                let (start, end) = (part * words / parts, (part + 1) * words / parts);
+                let mut bytes = Vec::with_capacity(8 + (end - start) * 8);
+                bytes.extend_from_slice(&(start as u64).to_le_bytes());
+                for word in &self.bitvec[start..end] {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+                bytes
+            })
+            .collect()
+    }
+
+    /// Reassembles a filter from its `config_to_bytes` header and all the `split_storage` parts,
+    /// in any order. Every word must be covered by exactly one part. \
+    /// Like `load_bits`, the `inserted` count is not restored, since it is not part of the bits.
+    pub fn join_storage<P: AsRef<[u8]>>(header: &[u8], parts: &[P]) -> Result<BloomFilter, BloomFilterError> {
+        let mut bloom_filter = Self::config_from_bytes(header)?;
+        let mut covered = vec![false; bloom_filter.bitvec.len()];
+
+        for part in parts {
+            let mut reader = ByteReader { bytes: part.as_ref() };
+            let start = reader.read_usize()?;
+            if !reader.bytes.len().is_multiple_of(8) {
+                return Err(BloomFilterError::InvalidFormat(String::from("part is not made of whole words")));
+            }
+            let end = start.saturating_add(reader.bytes.len() / 8);
+            if end > covered.len() || covered[start..end].iter().any(|&seen| seen) {
+                return Err(BloomFilterError::InvalidFormat(format!("part at word {} overlaps or exceeds the bit vector", start)));
+            }
+
+            for word in &mut bloom_filter.bitvec[start..end] {
+                *word = reader.read_u64()?;
+            }
+            covered[start..end].fill(true);
+        }
+        if covered.contains(&false) {
+            return Err(BloomFilterError::InvalidFormat(String::from("parts don't cover the whole bit vector")));
+        }
+        bloom_filter.validate()?;
+        Ok(bloom_filter)
+    }
+
+    /// Cheap self-check before persisting a critical filter: serializes it with `to_bytes`,
+    /// restores it with `from_bytes` and returns whether the copy has the same bits and every
+    /// `FilterConfig` field. The floats are compared bit for bit, so a NaN prob_fp round trips.
+    pub fn verify_roundtrip(&self) -> bool {
+        self.roundtrips_with(Self::to_bytes)
+    }
+
+    fn roundtrips_with<F: Fn(&BloomFilter) -> Vec<u8>>(&self, serialize: F) -> bool {
+        let Ok(restored) = Self::from_bytes(&serialize(self)) else {
+            return false;
+        };
+        // Destructured in full, so a field added to FilterConfig can't be left out here
+        let FilterConfig {
+            prob_fp,
+            data_set_size,
+            vector_len,
+            num_hashers,
+            seed,
+            index_strategy,
+            inserted,
+            strict,
+            fp_tolerance,
+            reject_empty,
+            branchless,
+        } = self.config();
+        let config = restored.config();
+
+        config.prob_fp.to_bits() == prob_fp.to_bits()
+            && config.data_set_size == data_set_size
+            && config.vector_len == vector_len
+            && config.num_hashers == num_hashers
+            && config.seed == seed
+            && config.index_strategy == index_strategy
+            && config.inserted == inserted
+            && config.strict == strict
+            && config.fp_tolerance.to_bits() == fp_tolerance.to_bits()
+            && config.reject_empty == reject_empty
+            && config.branchless == branchless
+            && restored.bitvec == self.bitvec
+    }
+
+    fn write_header(&self, bytes: &mut Vec<u8>, inserted: usize) {
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.prob_fp.to_le_bytes());
+        for field in [self.data_set_size, self.vector_len, self.num_hashers] {
+            bytes.extend_from_slice(&(field as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(inserted as u64).to_le_bytes());
+        bytes.push(self.strict as u8);
+        bytes.push(self.index_strategy.to_byte());
+        bytes.extend_from_slice(&self.fp_tolerance.to_le_bytes());
+
+        let mut flags = 0;
+        if self.reject_empty {
+            flags |= FLAG_REJECT_EMPTY;
+        }
+        if self.branchless {
+            flags |= FLAG_BRANCHLESS;
+        }
+        bytes.push(flags);
+    }
+
+    fn write_bits(&self, bytes: &mut Vec<u8>) {
+        for word in &self.bitvec {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// Parses a current version header
+    fn read_header(reader: &mut ByteReader) -> Result<Header, BloomFilterError> {
+        let version = reader.read_u8()?;
+        if version != FORMAT_VERSION {
+            return Err(BloomFilterError::UnsupportedVersion(version));
+        }
+
+        Self::read_header_fields(reader)
+    }
+
+    /// Parses the current version header fields following the version byte
+    fn read_header_fields(reader: &mut ByteReader) -> Result<Header, BloomFilterError> {
+        let mut header = Self::read_v1_header_fields(reader)?;
+        header.index_strategy = Self::read_index_strategy(reader)?;
+        header.fp_tolerance = f64::from_bits(reader.read_u64()?);
+
+        let flags = reader.read_u8()?;
+        if flags & !(FLAG_REJECT_EMPTY | FLAG_BRANCHLESS) != 0 {
+            return Err(BloomFilterError::InvalidFormat(format!("unknown flags {:#04x}", flags)));
+        }
+        header.reject_empty = flags & FLAG_REJECT_EMPTY != 0;
+        header.branchless = flags & FLAG_BRANCHLESS != 0;
+        Ok(header)
+    }
+
+    /// Parses the version 1 header fields following the version byte. Version 1 had no index
+    /// strategy, tolerance or flags: every filter used `IndexStrategy::Modulo` and the defaults.
+    fn read_v1_header_fields(reader: &mut ByteReader) -> Result<Header, BloomFilterError> {
+        let prob_fp = f64::from_bits(reader.read_u64()?);
+        let data_set_size = reader.read_usize()?;
+        let vector_len = reader.read_usize()?;
+        let num_hashers = reader.read_usize()?;
+        let seed = reader.read_u64()?;
+        let inserted = reader.read_usize()?;
+        let strict = reader.read_u8()? != 0;
+
+        if vector_len == 0 || num_hashers == 0 {
+            return Err(BloomFilterError::InvalidFormat(String::from("filter dimensions must be at least 1")));
+        }
+        if vector_len >= MAX_VECTOR_LEN {
+            return Err(BloomFilterError::FilterTooLarge(data_set_size));
+        }
+
+        Ok(Header {
+            prob_fp,
+            data_set_size,
+            vector_len,
+            num_hashers,
+            seed,
+            inserted,
+            strict,
+            index_strategy: IndexStrategy::Modulo,
+            fp_tolerance: DEFAULT_FP_TOLERANCE,
+            reject_empty: false,
+            branchless: false,
+        })
+    }
+
+    fn read_index_strategy(reader: &mut ByteReader) -> Result<IndexStrategy, BloomFilterError> {
+        let index_strategy = reader.read_u8()?;
+        IndexStrategy::from_byte(index_strategy)
+            .ok_or_else(|| BloomFilterError::InvalidFormat(format!("unknown index strategy {}", index_strategy)))
+    }
+
+    /// Reads exactly as many words as the filter holds, leaving self untouched on error
+    fn read_bits(&mut self, reader: &mut ByteReader) -> Result<(), BloomFilterError> {
+        let bitvec = reader.read_words(self.bitvec.len())?;
+        self.check_padding(&bitvec)?;
+
+        self.bitvec = bitvec;
+        Ok(())
+    }
+
+    /// The padding bits after vector_len in the last word must be clear
+    fn check_padding(&self, bitvec: &[u64]) -> Result<(), BloomFilterError> {
+        if !self.vector_len.is_multiple_of(64) && bitvec[bitvec.len() - 1] >> (self.vector_len % 64) != 0 {
+            return Err(BloomFilterError::InvalidFormat(String::from("bits set past vector_len")));
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for BloomFilter {
+    type Error = BloomFilterError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        BloomFilter::from_bytes(bytes)
+    }
+}
+
+impl From<&BloomFilter> for Vec<u8> {
+    fn from(bloom_filter: &BloomFilter) -> Self {
+        bloom_filter.to_bytes()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        bloom_filter.add("cat");
+        bloom_filter.add("dog");
+
+        let restored = BloomFilter::from_bytes(&bloom_filter.to_bytes()).unwrap();
+        assert!(restored.contains("cat"));
+        assert!(restored.contains("dog"));
+        assert!(!restored.contains("monkey"));
+        assert!(restored.is_compatible_with(&bloom_filter));
+        assert_eq!(restored.inserted(), 2);
+        assert_eq!(restored.seed(), bloom_filter.seed());
+    }
+
+    #[test]
+    fn streamed_round_trip() {
+        let mut bloom_filter = BloomFilter::new(0.001, 100_000);
+        for i in 0..1000 {
+            bloom_filter.add(i);
+        }
+
+        let mut streamed = Vec::new();
+        bloom_filter.write_to(&mut streamed).unwrap();
+        assert_eq!(streamed, bloom_filter.to_bytes());
+
+        streamed.extend_from_slice(b"next record");
+        let mut reader = io::Cursor::new(streamed);
+        let restored = BloomFilter::read_from(&mut reader).unwrap();
+        assert!((0..1000).all(|i| restored.contains(i)));
+        assert_eq!(restored.bitvec, bloom_filter.bitvec);
+        assert_eq!(&reader.get_ref()[reader.position() as usize..], b"next record");
+
+        let truncated = &bloom_filter.to_bytes()[..HEADER_LEN + 8];
+        assert_eq!(BloomFilter::read_from(truncated).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let mut wrong_version = bloom_filter.to_bytes();
+        wrong_version[0] = 9;
+        assert_eq!(BloomFilter::read_from(&wrong_version[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn split_parts_rejoin_in_any_order() {
+        let mut bloom_filter = BloomFilter::new(0.01, 1000);
+        for i in 0..500 {
+            bloom_filter.add(i);
+        }
+
+        let mut parts = bloom_filter.split_storage(4);
+        assert_eq!(parts.len(), 4);
+        parts.swap(0, 3);
+        parts.swap(1, 2);
+        let header = bloom_filter.config_to_bytes();
+        let joined = BloomFilter::join_storage(&header, &parts).unwrap();
+        assert_eq!(joined.bitvec, bloom_filter.bitvec);
+        assert!(joined.is_compatible_with(&bloom_filter));
+
+        assert!(BloomFilter::join_storage(&header, &parts[..3]).is_err());
+        let duplicated = [&parts[0], &parts[0], &parts[1], &parts[2], &parts[3]];
+        assert!(BloomFilter::join_storage(&header, &duplicated).is_err());
+    }
+
+    #[test]
+    fn verify_roundtrip_detects_divergence() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 100).strict(true).build().unwrap();
+        bloom_filter.add("cat");
+        assert!(bloom_filter.verify_roundtrip());
+
+        let flip_first_bit = |bloom_filter: &BloomFilter| {
+            let mut bytes = bloom_filter.to_bytes();
+            bytes[HEADER_LEN] ^= 1;
+            bytes
+        };
+        assert!(BloomFilter::from_bytes(&flip_first_bit(&bloom_filter)).is_ok());
+        assert!(!bloom_filter.roundtrips_with(flip_first_bit));
+        assert!(!bloom_filter.roundtrips_with(|bloom_filter| bloom_filter.config_to_bytes()));
+    }
+
+    #[test]
+    fn verify_roundtrip_compares_every_setting() {
+        let bloom_filter = BloomFilter::builder(0.01, 100).fp_tolerance(0.25).reject_empty(true).branchless_contains(true).build().unwrap();
+        assert!(bloom_filter.verify_roundtrip());
+
+        let drop_flags = |bloom_filter: &BloomFilter| {
+            let mut bytes = bloom_filter.to_bytes();
+            bytes[HEADER_LEN - 1] = 0;
+            bytes
+        };
+        assert!(!bloom_filter.roundtrips_with(drop_flags));
+        assert!(!bloom_filter.roundtrips_with(|bloom_filter| {
+            let mut bytes = bloom_filter.to_bytes();
+            bytes[V1_HEADER_LEN + 1..V1_HEADER_LEN + 9].copy_from_slice(&DEFAULT_FP_TOLERANCE.to_le_bytes());
+            bytes
+        }));
+
+        let nan = BloomFilter::from_raw_parts(f64::NAN, 100, 1000, 3, 42, vec![0; 16]).unwrap();
+        assert!(nan.verify_roundtrip());
+    }
+
+    /// A version 1 blob as written before IndexStrategy existed: the 50 byte header and one
+    /// byte per bit, with bits 3, 17 and 64 set
+    fn version_1_blob() -> Vec<u8> {
+        let mut v1 = vec![1];
+        v1.extend_from_slice(&0.01f64.to_le_bytes());
+        v1.extend_from_slice(&10u64.to_le_bytes());   // data_set_size
+        v1.extend_from_slice(&70u64.to_le_bytes());   // vector_len
+        v1.extend_from_slice(&3u64.to_le_bytes());    // num_hashers
+        v1.extend_from_slice(&42u64.to_le_bytes());   // seed
+        v1.extend_from_slice(&1u64.to_le_bytes());    // inserted
+        v1.push(0);                                   // strict
+        assert_eq!(v1.len(), V1_HEADER_LEN);
+        v1.extend((0..70).map(|index| [3, 17, 64].contains(&index) as u8));
+        v1
+    }
+
+    #[test]
+    fn version_1_blob_migrates_on_load() {
+        let v1 = version_1_blob();
+        let migrated = BloomFilter::from_bytes(&v1).unwrap();
+        assert_eq!((migrated.vector_len, migrated.num_hashers, migrated.seed), (70, 3, 42));
+        assert_eq!((migrated.data_set_size, migrated.inserted, migrated.strict), (10, 1, false));
+        assert_eq!(migrated.index_strategy(), IndexStrategy::Modulo);
+        assert_eq!(migrated.fp_tolerance, DEFAULT_FP_TOLERANCE);
+        assert!(!migrated.reject_empty && !migrated.branchless);
+        assert_eq!(migrated.bitvec, vec![1 << 3 | 1 << 17, 1]);
+        assert_eq!(migrated.to_bytes()[0], FORMAT_VERSION);
+
+        let mut invalid_bit = v1.clone();
+        invalid_bit[V1_HEADER_LEN + 5] = 7;
+        assert!(matches!(BloomFilter::from_bytes(&invalid_bit), Err(BloomFilterError::InvalidFormat(_))));
+        assert!(matches!(BloomFilter::from_bytes(&v1[..v1.len() - 1]), Err(BloomFilterError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn version_1_blob_with_extra_byte_is_rejected() {
+        // Whatever the extra byte holds, a version 1 blob is its header and one byte per bit
+        let mut longer = version_1_blob();
+        longer.push(1);
+        assert!(matches!(BloomFilter::from_bytes(&longer), Err(BloomFilterError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn bit_words_are_little_endian() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        bloom_filter.set_bit(0);
+        bloom_filter.set_bit(9);
+        bloom_filter.set_bit(63);
+        let word: u64 = 1 | 1 << 9 | 1 << 63;
+
+        let mut bytes = bloom_filter.to_bytes();
+        assert_eq!(bytes[HEADER_LEN..HEADER_LEN + 8], word.to_le_bytes());
+
+        // A blob with the first word in big-endian order is read as little-endian all the same,
+        // so the loader sees the byte-swapped word rather than depending on the host.
+        bytes[HEADER_LEN..HEADER_LEN + 8].reverse();
+        let swapped = BloomFilter::from_bytes(&bytes).unwrap();
+        assert_eq!(swapped.bitvec[0], word.swap_bytes());
+        assert!(swapped.get_bit(56) && swapped.get_bit(49) && swapped.get_bit(7));
+        assert_eq!(swapped.count_set_bits(), 3);
+    }
+
+    #[test]
+    fn index_strategy_survives_round_trip() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 100).index_strategy(IndexStrategy::FastRange).build().unwrap();
+        bloom_filter.add("cat");
+
+        let restored = BloomFilter::from_bytes(&bloom_filter.to_bytes()).unwrap();
+        assert_eq!(restored.index_strategy(), IndexStrategy::FastRange);
+        assert!(restored.contains("cat"));
+    }
+
+    #[test]
+    fn config_and_bits_travel_separately() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 100).index_strategy(IndexStrategy::FastRange).build().unwrap();
+        bloom_filter.add("cat");
+        bloom_filter.add("dog");
+
+        let config = bloom_filter.config_to_bytes();
+        assert_eq!(config.len(), HEADER_LEN);
+        let mut rebuilt = BloomFilter::config_from_bytes(&config).unwrap();
+        assert!(rebuilt.is_compatible_with(&bloom_filter));
+        assert_eq!(rebuilt.count_set_bits(), 0);
+        assert_eq!(rebuilt.inserted(), 0);
+
+        rebuilt.load_bits(&bloom_filter.bits_as_bytes()).unwrap();
+        for animal in ["cat", "dog", "fox", "monkey"] {
+            assert_eq!(rebuilt.contains(animal), bloom_filter.contains(animal));
+        }
+
+        assert!(BloomFilter::config_from_bytes(&bloom_filter.to_bytes()).is_err());
+        assert!(rebuilt.load_bits(&[0; 3]).is_err());
+        assert!(rebuilt.contains("cat"));
+    }
+
+    #[test]
+    fn conversion_traits_round_trip() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        bloom_filter.add("cat");
+
+        let bytes = Vec::<u8>::from(&bloom_filter);
+        let restored = BloomFilter::try_from(bytes.as_slice()).unwrap();
+        assert!(restored.contains("cat"));
+        assert_eq!(Vec::<u8>::from(&restored), bytes);
+    }
+
+    #[test]
+    fn huge_claimed_dimensions_fail_without_allocating() {
+        let mut header = BloomFilter::new(0.01, 100).config_to_bytes();
+        header[1 + 8 * 2..1 + 8 * 3].copy_from_slice(&(1u64 << 62).to_le_bytes());   // vector_len
+        assert_eq!(BloomFilter::from_bytes(&header).unwrap_err(), BloomFilterError::FilterTooLarge(100));
+        assert_eq!(BloomFilter::config_from_bytes(&header).unwrap_err(), BloomFilterError::FilterTooLarge(100));
+        assert_eq!(BloomFilter::read_from(&header[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Below the addressable limit, the short input is what gives the claim away
+        header[1 + 8 * 2..1 + 8 * 3].copy_from_slice(&(1u64 << 50).to_le_bytes());
+        assert!(matches!(BloomFilter::from_bytes(&header), Err(BloomFilterError::InvalidFormat(_))));
+        assert_eq!(BloomFilter::read_from(&header[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let mut v1 = header.clone();
+        v1[0] = 1;
+        assert!(matches!(BloomFilter::from_bytes(&v1), Err(BloomFilterError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn header_layout_is_pinned() {
+        let bloom_filter = BloomFilter::builder(0.01, 100)
+            .strict(true)
+            .index_strategy(IndexStrategy::FastRange)
+            .fp_tolerance(0.25)
+            .branchless_contains(true)
+            .build()
+            .unwrap();
+
+        // Changing any of these without bumping FORMAT_VERSION breaks blobs already written
+        let mut expected = vec![2];
+        expected.extend_from_slice(&0.01f64.to_le_bytes());
+        for field in [100, bloom_filter.vector_len as u64, bloom_filter.num_hashers as u64, bloom_filter.seed, 0] {
+            expected.extend_from_slice(&field.to_le_bytes());
+        }
+        expected.extend_from_slice(&[1, 1]);   // strict, FastRange
+        expected.extend_from_slice(&0.25f64.to_le_bytes());
+        expected.push(FLAG_BRANCHLESS);
+        assert_eq!(bloom_filter.config_to_bytes(), expected);
+        assert_eq!(expected.len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn settings_survive_round_trip() {
+        let mut bloom_filter = BloomFilter::builder(0.01, 100).fp_tolerance(0.25).reject_empty(true).branchless_contains(true).build().unwrap();
+        bloom_filter.add("cat");
+
+        let restored = BloomFilter::from_bytes(&bloom_filter.to_bytes()).unwrap();
+        assert_eq!(restored.fp_tolerance, 0.25);
+        assert!(restored.reject_empty && restored.branchless);
+        assert!(restored.contains("cat") && !restored.contains(""));
+
+        let mut unknown_flag = bloom_filter.to_bytes();
+        unknown_flag[HEADER_LEN - 1] |= 1 << 2;
+        assert!(matches!(BloomFilter::from_bytes(&unknown_flag), Err(BloomFilterError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        let bytes = BloomFilter::new(0.01, 100).to_bytes();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 99;
+        assert_eq!(BloomFilter::try_from(wrong_version.as_slice()).unwrap_err(), BloomFilterError::UnsupportedVersion(99));
+
+        assert!(matches!(BloomFilter::from_bytes(&bytes[..bytes.len() - 1]), Err(BloomFilterError::InvalidFormat(_))));
+        assert!(matches!(BloomFilter::from_bytes(&[]), Err(BloomFilterError::InvalidFormat(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::hash::{Hash, Hasher};
+
+
+/// How a key is fed to the filter's hashers. \
+/// Every `Hash` type gets it for free; implement it by hand for key types that can't or shouldn't
+/// implement `Hash`, such as floats or keys needing canonicalization. Equal keys must feed
+/// the hasher the same bytes, otherwise `contains` misses items that were added.
+pub trait BloomHash {
+    fn bloom_hash(&self, hasher: &mut impl Hasher);
+
+    /// Whether this is an empty key, which filters built with `reject_empty` never insert and
+    /// never report present. \
+    /// The default looks at what `bloom_hash` writes, not at any hash: a key is empty if it writes
+    /// exactly what an empty string or an empty slice does. Override it for key types with their
+    /// own notion of empty.
+    fn is_empty_key(&self) -> bool {
+        let mut probe = EmptyProbe::Start;
+        self.bloom_hash(&mut probe);
+        probe == EmptyProbe::Empty
+    }
+}
+
+impl<T: Hash + ?Sized> BloomHash for T {
+    fn bloom_hash(&self, hasher: &mut impl Hasher) {
+        self.hash(hasher);
+    }
+}
+
+/// Hasher following the writes of a key, to tell an empty string (no bytes, then the 0xff
+/// terminator of `Hasher::write_str`) or an empty slice (a zero length prefix, then no bytes)
+/// apart from keys that merely produce the same byte stream, like `255u8` or `0usize`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EmptyProbe {
+    Start,
+    NoBytes,      // an empty write, as the start of an empty string
+    ZeroLength,   // a zero length prefix, as the start of an empty slice
+    Empty,
+    NotEmpty,
+}
+
+impl Hasher for EmptyProbe {
+    fn write(&mut self, bytes: &[u8]) {
+        *self = match (*self, bytes.is_empty()) {
+            (EmptyProbe::Start, true) => EmptyProbe::NoBytes,
+            (EmptyProbe::ZeroLength, true) => EmptyProbe::Empty,
+            _ => EmptyProbe::NotEmpty,
+        };
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        *self = if *self == EmptyProbe::NoBytes && i == 0xff { EmptyProbe::Empty } else { EmptyProbe::NotEmpty };
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        *self = if *self == EmptyProbe::Start && i == 0 { EmptyProbe::ZeroLength } else { EmptyProbe::NotEmpty };
+    }
+
+    fn finish(&self) -> u64 {
+        0   // never consulted, only the state matters
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BloomFilter, BloomSet, CachedBloomFilter, DeletableBloomFilter, FrozenBloomFilter};
+
+    /// f64 key treating -0.0 as 0.0 and every NaN as the same value
+    struct Float(f64);
+
+    impl BloomHash for Float {
+        fn bloom_hash(&self, hasher: &mut impl Hasher) {
+            let canonical = if self.0.is_nan() {
+                f64::NAN
+            } else if self.0 == 0.0 {
+                0.0
+            } else {
+                self.0
+            };
+            hasher.write_u64(canonical.to_bits());
+        }
+    }
+
+    #[test]
+    fn empty_keys_are_told_apart_from_same_bytes() {
+        assert!("".is_empty_key());
+        assert!(String::new().is_empty_key());
+        assert!(<[u8]>::is_empty_key(&[]));
+        assert!(Vec::<u32>::new().is_empty_key());
+
+        assert!(!"a".is_empty_key());
+        assert!(!0u64.is_empty_key());
+        assert!(!0usize.is_empty_key());
+        assert!(!255u8.is_empty_key());
+        assert!(!().is_empty_key());
+        assert!(!("", "").is_empty_key());
+        assert!(!Float(0.0).is_empty_key());
+    }
+
+    #[test]
+    fn floats_are_found_after_canonicalization() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        bloom_filter.add(Float(1.5));
+        bloom_filter.add(Float(0.0));
+        bloom_filter.add(Float(-f64::NAN));
+
+        assert!(bloom_filter.contains(Float(1.5)));
+        assert!(bloom_filter.contains(Float(-0.0)));
+        assert!(bloom_filter.contains(Float(f64::NAN)));
+        assert!(!bloom_filter.contains(Float(2.5)));
+    }
+
+    #[test]
+    fn companion_types_hash_like_the_filter() {
+        let frozen = FrozenBloomFilter::from_exact_items(0.01, [Float(1.5), Float(0.0)]).unwrap();
+        assert!(frozen.contains(Float(-0.0)) && frozen.contains(Float(1.5)));
+
+        let mut bloom_set = BloomSet::new(0.01, 100);
+        assert!(bloom_set.insert(Float(f64::NAN)).was_new());
+        assert!(bloom_set.contains(&Float(-f64::NAN)));
+
+        let mut deletable = DeletableBloomFilter::new(0.01, 100);
+        deletable.add(Float(0.0));
+        assert!(deletable.contains(Float(-0.0)));
+        assert!(deletable.remove(Float(-0.0)));
+
+        let mut cached = CachedBloomFilter::new(BloomFilter::new(0.01, 100), 4);
+        cached.add(Float(2.5));
+        assert!(cached.contains(Float(2.5)));
+        assert_eq!(cached.inner().compute_hashes(Float(2.5)), cached.inner().compute_hashes(2.5f64.to_bits()));
+    }
+}
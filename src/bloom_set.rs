@@ -0,0 +1,109 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+use crate::{BloomFilter, BloomHash};
+
+
+/// Set-like wrapper over a [`BloomFilter`] for values of type `T`, mirroring `HashSet::insert`,
+/// `contains`, `len` and `is_empty` to ease switching from a `HashSet`. `insert` wraps its answer
+/// in an [`InsertOutcome`] so that it can't be dropped by accident.
+///
+/// The answers are probabilistic: `contains` may report a value that was never inserted
+/// (a false positive, at roughly the filter's `prob_fp` rate) but never misses one that was.
+/// For the same reason `insert` may report a new value as already present, so `len` can
+/// undercount the number of distinct values.
+#[derive(Clone, Debug)]
+pub struct BloomSet<T> {
+    bloom_filter: BloomFilter,
+    len: usize,
+    marker: PhantomData<fn(T)>,   // only hashes T, doesn't own any
+}
+
+/// Whether `BloomSet::insert` added a value that was not present before. \
+/// Ignoring it is flagged by `unused_must_use`:
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// use bloom_filter_rs::BloomSet;
+///
+/// let mut bloom_set = BloomSet::new(0.01, 100);
+/// bloom_set.insert("cat");
+/// ```
+#[must_use]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InsertOutcome {
+    was_new: bool,
+}
+
+impl InsertOutcome {
+
+    /// Whether the value was definitely not present before
+    pub fn was_new(self) -> bool {
+        self.was_new
+    }
+}
+
+impl<T: BloomHash> BloomSet<T> {
+
+    /// Empty set sized for data_set_size values at false positive probability prob_fp
+    pub fn new(prob_fp: f64, data_set_size: usize) -> Self {
+        BloomSet {
+            bloom_filter: BloomFilter::new(prob_fp, data_set_size),
+            len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Adds a value, reporting whether it was not present before
+    pub fn insert(&mut self, value: T) -> InsertOutcome {
+        let was_new = matches!(self.bloom_filter.try_add(value), Ok(true));
+        if was_new {
+            self.len += 1;
+        }
+        InsertOutcome { was_new }
+    }
+
+    /// Whether the value is (probably) in the set. Like `HashSet`, accepts any borrowed form of `T`.
+    #[must_use]
+    pub fn contains<Q: BloomHash + ?Sized>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.bloom_filter.contains_ref(value)
+    }
+
+    /// Number of values inserted as new
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no value was inserted yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn behaves_like_hash_set_for_present_items() {
+        let mut hash_set: HashSet<String> = HashSet::new();
+        let mut bloom_set: BloomSet<String> = BloomSet::new(0.001, 100);
+        assert!(bloom_set.is_empty());
+
+        for animal in ["cat", "dog", "cat", "fox"] {
+            assert_eq!(bloom_set.insert(animal.to_string()).was_new(), hash_set.insert(animal.to_string()));
+        }
+        for animal in ["cat", "dog", "fox"] {
+            assert!(hash_set.contains(animal));
+            assert!(bloom_set.contains(animal));
+            assert!(bloom_set.contains(&animal.to_string()));
+        }
+        assert_eq!(bloom_set.len(), hash_set.len());
+        assert!(!bloom_set.is_empty());
+    }
+}
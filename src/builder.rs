@@ -0,0 +1,72 @@
+use crate::bloom_filter::DEFAULT_FP_TOLERANCE;
+use crate::{BloomFilter, BloomFilterError, IndexStrategy};
+
+
+/// Configures a [`BloomFilter`] with options beyond `prob_fp` and `data_set_size`. \
+/// Created with [`BloomFilter::builder`]
+#[derive(Clone, Debug)]
+pub struct BloomFilterBuilder {
+    pub(crate) prob_fp: f64,
+    pub(crate) data_set_size: usize,
+    pub(crate) strict: bool,
+    pub(crate) index_strategy: IndexStrategy,
+    pub(crate) fp_tolerance: f64,
+    pub(crate) reject_empty: bool,
+    pub(crate) branchless: bool,
+}
+
+impl BloomFilterBuilder {
+
+    pub(crate) fn new(prob_fp: f64, data_set_size: usize) -> Self {
+        BloomFilterBuilder {
+            prob_fp,
+            data_set_size,
+            strict: false,
+            index_strategy: IndexStrategy::default(),
+            fp_tolerance: DEFAULT_FP_TOLERANCE,
+            reject_empty: false,
+            branchless: false,
+        }
+    }
+
+    /// In strict mode, inserts beyond `data_set_size` fail with `CapacityExceeded` \
+    /// instead of silently raising the false positive rate. Off by default.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// How hashes are mapped onto bit indexes. Defaults to `IndexStrategy::Modulo`.
+    pub fn index_strategy(mut self, index_strategy: IndexStrategy) -> Self {
+        self.index_strategy = index_strategy;
+        self
+    }
+
+    /// With the `strict-debug` feature, debug builds panic when a filter loaded to `data_set_size`
+    /// has a false positive rate above `prob_fp * (1 + tolerance)`, which points at a mis-sized filter.
+    /// Defaults to 1.0. Filters under 1024 bits are not checked, their fill is too noisy to judge.
+    pub fn fp_tolerance(mut self, tolerance: f64) -> Self {
+        self.fp_tolerance = tolerance;
+        self
+    }
+
+    /// Treat empty keys, like `""` or an empty byte slice, as never present: adding one inserts
+    /// nothing and looking one up is always false. Off by default, empty keys hash like any other.
+    pub fn reject_empty(mut self, reject_empty: bool) -> Self {
+        self.reject_empty = reject_empty;
+        self
+    }
+
+    /// Make lookups test all `num_hashers` bits and AND the results, instead of returning at the
+    /// first clear bit. Can be faster for very dense, high false positive filters, where lookups
+    /// rarely stop early. Off by default; the answers are the same either way.
+    pub fn branchless_contains(mut self, branchless: bool) -> Self {
+        self.branchless = branchless;
+        self
+    }
+
+    /// Validates the parameters and builds the filter
+    pub fn build(&self) -> Result<BloomFilter, BloomFilterError> {
+        BloomFilter::from_builder(self)
+    }
+}
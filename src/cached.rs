@@ -0,0 +1,155 @@
+use std::hash::Hasher;
+
+use crate::{BloomFilter, BloomHash};
+
+
+/// Wraps a [`BloomFilter`] with a small cache of the base hashes of recently seen keys,
+/// so repeated lookups of hot keys skip the two keyed base hashes. \
+/// The cache is a fixed-size ring buffer: once full, the oldest entry is overwritten.
+///
+/// Entries are keyed on the exact bytes the key feeds its `BloomHash` impl, so a hit is only taken
+/// for a key that hashes identically; no digest collision can hand out another key's hashes.
+#[derive(Clone, Debug)]
+pub struct CachedBloomFilter {
+    bloom_filter: BloomFilter,
+    cache: Vec<(Vec<u8>, (u64, u64))>,   // (key bytes, base hashes)
+    capacity: usize,
+    next: usize,                         // ring buffer slot to overwrite next
+    scratch: KeyBytes,                   // reused to record the key of each lookup
+}
+
+/// Hasher recording the bytes a key writes instead of hashing them
+#[derive(Clone, Debug, Default)]
+struct KeyBytes(Vec<u8>);
+
+impl Hasher for KeyBytes {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        0   // never consulted, entries compare the recorded bytes
+    }
+}
+
+impl CachedBloomFilter {
+
+    /// Wrap bloom_filter, caching the base hashes of up to `capacity` keys
+    pub fn new(bloom_filter: BloomFilter, capacity: usize) -> Self {
+        CachedBloomFilter {
+            bloom_filter,
+            cache: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+            scratch: KeyBytes::default(),
+        }
+    }
+
+    /// Same as [`BloomFilter::add`], caching the key's base hashes
+    pub fn add<T: BloomHash>(&mut self, data: T) {
+        if self.bloom_filter.rejects(&data) {
+            return;
+        }
+        let hashes = self.hashes(&data);
+        self.bloom_filter.add_hashes(hashes);
+    }
+
+    /// Same as [`BloomFilter::contains`], caching the key's base hashes
+    #[must_use]
+    pub fn contains<T: BloomHash>(&mut self, data: T) -> bool {
+        if self.bloom_filter.rejects(&data) {
+            return false;
+        }
+        let hashes = self.hashes(&data);
+        self.bloom_filter.contains_hashes(hashes)
+    }
+
+    /// Number of keys whose base hashes are currently cached
+    pub fn cached_len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// The wrapped filter
+    pub fn inner(&self) -> &BloomFilter {
+        &self.bloom_filter
+    }
+
+    /// Unwrap the filter, dropping the cache
+    pub fn into_inner(self) -> BloomFilter {
+        self.bloom_filter
+    }
+
+    fn hashes<T: BloomHash>(&mut self, data: &T) -> (u64, u64) {
+        self.scratch.0.clear();
+        data.bloom_hash(&mut self.scratch);
+        let key = &self.scratch.0;
+
+        if let Some(&(_, hashes)) = self.cache.iter().find(|(cached_key, _)| cached_key == key) {
+            return hashes;
+        }
+
+        let hashes = self.bloom_filter.hashes_of(data);
+        if self.capacity > 0 {
+            let entry = (key.clone(), hashes);
+            if self.cache.len() < self.capacity {
+                self.cache.push(entry);
+            } else {
+                self.cache[self.next] = entry;
+            }
+            self.next = (self.next + 1) % self.capacity;
+        }
+        hashes
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::Hash;
+
+    #[test]
+    fn cached_matches_uncached() {
+        let mut bloom_filter = BloomFilter::new(0.01, 100);
+        for animal in ["cat", "dog", "fox"] {
+            bloom_filter.add(animal);
+        }
+        let mut cached = CachedBloomFilter::new(bloom_filter.clone(), 2);
+        assert_eq!(cached.cached_len(), 0);
+
+        assert!(cached.contains("cat"));
+        assert_eq!(cached.cached_len(), 1);
+        assert!(cached.contains("cat"));
+        assert_eq!(cached.cached_len(), 1);
+
+        for animal in ["cat", "dog", "fox", "monkey", "cat", "monkey"] {
+            assert_eq!(cached.contains(animal), bloom_filter.contains(animal));
+        }
+        assert_eq!(cached.cached_len(), 2);
+
+        cached.add("monkey");
+        assert!(cached.contains("monkey"));
+        assert!(cached.into_inner().contains("monkey"));
+    }
+
+    #[test]
+    fn entries_are_keyed_on_whole_keys() {
+        let mut bloom_filter = BloomFilter::new_with_key(0.01, 100, b"key");
+        bloom_filter.add(("ab", "c"));
+        let mut cached = CachedBloomFilter::new(bloom_filter.clone(), 4);
+
+        // Same characters, split differently: distinct keys, so distinct entries
+        assert!(cached.contains(("ab", "c")));
+        assert_eq!(cached.contains(("a", "bc")), bloom_filter.contains(("a", "bc")));
+        assert_eq!(cached.cached_len(), 2);
+        assert_eq!(cached.cache[0].0, {
+            let mut key = KeyBytes::default();
+            ("ab", "c").hash(&mut key);
+            key.0
+        });
+
+        for i in 0..1000 {
+            assert_eq!(cached.contains(i), bloom_filter.contains(i));
+        }
+    }
+}
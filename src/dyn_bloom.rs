@@ -0,0 +1,70 @@
+use crate::{BloomFilter, DeletableBloomFilter};
+
+
+/// Object-safe interface over the filter variants, so different kinds can be stored side by
+/// side as `Box<dyn DynBloom>`. \
+/// Keys are raw bytes, since generic `add`/`contains` can't be called through a trait object.
+pub trait DynBloom {
+    /// Adds the raw content of a byte slice
+    fn add_bytes(&mut self, bytes: &[u8]);
+
+    /// Checks whether the raw content of a byte slice was added
+    #[must_use]
+    fn contains_bytes(&self, bytes: &[u8]) -> bool;
+
+    /// Fraction of bits set, between 0.0 and 1.0
+    fn fill_ratio(&self) -> f64;
+}
+
+impl DynBloom for BloomFilter {
+    fn add_bytes(&mut self, bytes: &[u8]) {
+        BloomFilter::add_bytes(self, bytes);
+    }
+
+    fn contains_bytes(&self, bytes: &[u8]) -> bool {
+        BloomFilter::contains_bytes(self, bytes)
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        BloomFilter::fill_ratio(self)
+    }
+}
+
+impl DynBloom for DeletableBloomFilter {
+    fn add_bytes(&mut self, bytes: &[u8]) {
+        DeletableBloomFilter::add_bytes(self, bytes);
+    }
+
+    fn contains_bytes(&self, bytes: &[u8]) -> bool {
+        DeletableBloomFilter::contains_bytes(self, bytes)
+    }
+
+    fn fill_ratio(&self) -> f64 {
+        self.as_bloom_filter().fill_ratio()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_are_queried_through_trait_objects() {
+        let mut filters: Vec<Box<dyn DynBloom>> = vec![
+            Box::new(BloomFilter::new_with_key(0.01, 100, b"key")),
+            Box::new(DeletableBloomFilter::new(0.01, 100)),
+        ];
+
+        for filter in &mut filters {
+            assert_eq!(filter.fill_ratio(), 0.0);
+            filter.add_bytes(b"cat");
+            filter.add_bytes(b"dog");
+        }
+        for filter in &filters {
+            assert!(filter.contains_bytes(b"cat"));
+            assert!(filter.contains_bytes(b"dog"));
+            assert!(filter.fill_ratio() > 0.0);
+        }
+    }
+}
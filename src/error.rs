@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt;
+
+
+/// Errors returned by fallible bloom filter operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum BloomFilterError {
+    /// False positive probability that is NaN, infinite or outside of the open interval (0, 1)
+    InvalidProbability(f64),
+    /// Data set size of zero
+    InvalidDataSetSize,
+    /// The bit vector needed for this many items at the requested `prob_fp` is too large to address
+    FilterTooLarge(usize),
+    /// A filter without bits or without hashers
+    InvalidDimensions { vector_len: usize, num_hashers: usize },
+    /// The bits of a filter, this many bytes, could not be allocated
+    AllocationFailed(usize),
+    /// A memory budget too small to hold a single u64 word of bits
+    MemoryBudgetTooSmall(usize),
+    /// A strict filter already holds its `data_set_size` items
+    CapacityExceeded(usize),
+    /// A bit index past the end of the storage of a filter assembled from inconsistent raw parts,
+    /// or storage too short for `vector_len`
+    IndexOutOfRange { index: usize, storage_bits: usize },
+    /// Filters differ in a parameter that determines bit positions, so their bits can't be combined
+    IncompatibleFilters(String),
+    /// The operation needs a power of two `vector_len` of at least 2
+    NotPowerOfTwo(usize),
+    /// Serialized data was written with a format version this build can't read
+    UnsupportedVersion(u8),
+    /// Serialized data is truncated or inconsistent
+    InvalidFormat(String),
+    /// An operation over a collection of filters was given none
+    NoFilters,
+    /// The included and excluded sets of a filter cascade share an item
+    OverlappingSets,
+    /// A filter cascade still had false positives after this many levels
+    TooManyLevels(usize),
+}
+
+impl fmt::Display for BloomFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BloomFilterError::InvalidProbability(prob_fp) => write!(f, "prob_fp must be in (0, 1), got {}", prob_fp),
+            BloomFilterError::InvalidDataSetSize => write!(f, "data_set_size must be at least 1"),
+            BloomFilterError::FilterTooLarge(data_set_size) => write!(f, "a filter for {} items needs more bits than can be addressed", data_set_size),
+            BloomFilterError::InvalidDimensions { vector_len, num_hashers } => write!(f, "vector_len and num_hashers must be at least 1, got {} and {}", vector_len, num_hashers),
+            BloomFilterError::AllocationFailed(bytes) => write!(f, "failed to allocate {} bytes of bits", bytes),
+            BloomFilterError::MemoryBudgetTooSmall(max_bytes) => write!(f, "memory budget of {} bytes can't hold a single 8 byte word", max_bytes),
+            BloomFilterError::CapacityExceeded(data_set_size) => write!(f, "filter already holds its {} items", data_set_size),
+            BloomFilterError::IndexOutOfRange { index, storage_bits } => write!(f, "bit index {} out of range for {} bits of storage", index, storage_bits),
+            BloomFilterError::IncompatibleFilters(reason) => write!(f, "incompatible filters: {}", reason),
+            BloomFilterError::NotPowerOfTwo(vector_len) => write!(f, "vector_len must be a power of two of at least 2, got {}", vector_len),
+            BloomFilterError::UnsupportedVersion(version) => write!(f, "unsupported format version {}", version),
+            BloomFilterError::InvalidFormat(reason) => write!(f, "invalid serialized filter: {}", reason),
+            BloomFilterError::NoFilters => write!(f, "no filters were given"),
+            BloomFilterError::OverlappingSets => write!(f, "included and excluded sets overlap"),
+            BloomFilterError::TooManyLevels(levels) => write!(f, "filter cascade did not converge within {} levels, try a lower prob_fp", levels),
+        }
+    }
+}
+
+impl Error for BloomFilterError {}
@@ -0,0 +1,61 @@
+use crate::{BloomFilter, BloomFilterError, BloomHash};
+
+
+/// Read-only [`BloomFilter`] built once from a known collection. \
+/// It only offers lookups, so the compiler rejects any attempt to add items later:
+///
+/// ```compile_fail
+/// use bloom_filter_rs::FrozenBloomFilter;
+///
+/// let mut frozen = FrozenBloomFilter::from_exact_items(0.01, ["cat", "dog"]).unwrap();
+/// frozen.add("fox");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrozenBloomFilter {
+    bloom_filter: BloomFilter,
+}
+
+impl FrozenBloomFilter {
+
+    /// Filter sized for exactly the number of items given, at false positive probability prob_fp,
+    /// and holding all of them. \
+    /// Fails like `BloomFilter::try_new` for an invalid prob_fp or an empty collection.
+    pub fn from_exact_items<T: BloomHash, I: IntoIterator<Item = T>>(prob_fp: f64, items: I) -> Result<Self, BloomFilterError> {
+        let items: Vec<T> = items.into_iter().collect();
+        let mut bloom_filter = BloomFilter::try_new(prob_fp, items.len())?;
+        for item in items {
+            bloom_filter.add(item);
+        }
+        Ok(FrozenBloomFilter { bloom_filter })
+    }
+
+    /// Checks whether data is (probably) one of the items the filter was built from
+    #[must_use]
+    pub fn contains<T: BloomHash>(&self, data: T) -> bool {
+        self.bloom_filter.contains(data)
+    }
+
+    /// The underlying filter, for read-only metrics like `fill_ratio`
+    pub fn as_bloom_filter(&self) -> &BloomFilter {
+        &self.bloom_filter
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sized_for_exact_items_and_finds_them() {
+        let animals = vec!["cat", "dog", "fox", "owl"];
+        let frozen = FrozenBloomFilter::from_exact_items(0.01, animals.clone()).unwrap();
+
+        for animal in &animals {
+            assert!(frozen.contains(animal));
+        }
+        assert_eq!(frozen.as_bloom_filter().inserted(), animals.len());
+        assert_eq!(frozen.as_bloom_filter().vector_len(), BloomFilter::optimal_params(0.01, animals.len()).unwrap().0);
+        assert_eq!(FrozenBloomFilter::from_exact_items(0.01, Vec::<&str>::new()).unwrap_err(), BloomFilterError::InvalidDataSetSize);
+    }
+}
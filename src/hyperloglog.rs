@@ -0,0 +1,81 @@
+/// Index bits of the register array, `2^PRECISION` registers with a standard error of about
+/// `1.04 / sqrt(2^PRECISION)`, 0.8%
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+
+/// Minimal HyperLogLog distinct counter fed with the filters' 64-bit base hashes. \
+/// Unlike the fill-based `estimate_cardinality`, it keeps counting accurately long after the
+/// bit vector has saturated.
+#[derive(Clone, Debug)]
+pub(crate) struct HyperLogLog {
+    registers: Vec<u8>,   // longest run of leading zeros + 1 seen per register
+}
+
+impl HyperLogLog {
+
+    /// Heap bytes of a counter's registers, one byte each
+    pub(crate) const HEAP_BYTES: usize = REGISTERS;
+
+    pub(crate) fn new() -> Self {
+        HyperLogLog { registers: vec![0; REGISTERS] }
+    }
+
+    /// Heap bytes actually held by the registers
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.registers.capacity()
+    }
+
+    /// Records a 64-bit hash: its top bits pick the register, the rest gives the rank
+    pub(crate) fn insert(&mut self, hash: u64) {
+        let register = (hash >> (64 - PRECISION)) as usize;
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    /// Combines two counters as if every hash had been inserted into both
+    pub(crate) fn merge(&mut self, other: &HyperLogLog) {
+        for (register, &other_register) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other_register);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.registers.fill(0);
+    }
+
+    /// Estimated number of distinct hashes inserted, with linear counting for small counts
+    pub(crate) fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&register| f64::powi(2.0, -(register as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&register| register == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            return f64::round(m * f64::ln(m / zeros as f64)) as u64;
+        }
+        f64::round(estimate) as u64
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    #[test]
+    fn counts_distinct_hashes() {
+        let mut hyperloglog = HyperLogLog::new();
+        assert_eq!(hyperloglog.estimate(), 0);
+
+        for i in 0..50_000u64 {
+            let mut hasher = DefaultHasher::new();
+            (i % 10_000).hash(&mut hasher);
+            hyperloglog.insert(hasher.finish());
+        }
+        assert!(hyperloglog.estimate().abs_diff(10_000) <= 300, "estimate {}", hyperloglog.estimate());
+    }
+}
@@ -0,0 +1,68 @@
+/// How a 64-bit hash is mapped onto a bit index in `0..vector_len`. \
+/// Filters can only be combined when they use the same strategy, since it decides which bits an item sets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IndexStrategy {
+    /// `hash % vector_len`
+    #[default]
+    Modulo,
+    /// Lemire's multiply-shift range reduction `(hash * vector_len) >> 64`, which avoids the division
+    FastRange,
+    /// Enhanced double hashing: probes `h1 + i * h2 + i^3` instead of `h1 + i * h2`, which spreads
+    /// the probes of high `num_hashers` filters more evenly, then `% vector_len`
+    EnhancedDoubleHash,
+}
+
+impl IndexStrategy {
+
+    /// The i-th probe hash of an item with base hashes hash_1 and hash_2
+    pub(crate) fn probe(self, hash_1: u64, hash_2: u64, i: u64) -> u64 {
+        let probe = hash_1.wrapping_add(i.wrapping_mul(hash_2));
+        match self {
+            IndexStrategy::Modulo | IndexStrategy::FastRange => probe,
+            IndexStrategy::EnhancedDoubleHash => probe.wrapping_add(i.wrapping_mul(i).wrapping_mul(i)),
+        }
+    }
+
+    pub(crate) fn index(self, hash: u64, vector_len: usize) -> usize {
+        match self {
+            IndexStrategy::Modulo | IndexStrategy::EnhancedDoubleHash => (hash % vector_len as u64) as usize,
+            IndexStrategy::FastRange => ((hash as u128 * vector_len as u128) >> 64) as usize,
+        }
+    }
+
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            IndexStrategy::Modulo => 0,
+            IndexStrategy::FastRange => 1,
+            IndexStrategy::EnhancedDoubleHash => 2,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(IndexStrategy::Modulo),
+            1 => Some(IndexStrategy::FastRange),
+            2 => Some(IndexStrategy::EnhancedDoubleHash),
+            _ => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexes_stay_in_range() {
+        for strategy in [IndexStrategy::Modulo, IndexStrategy::FastRange, IndexStrategy::EnhancedDoubleHash] {
+            for hash in [0, 1, 12345, u64::MAX / 2, u64::MAX] {
+                assert!(strategy.index(hash, 1000) < 1000);
+            }
+            assert_eq!(IndexStrategy::from_byte(strategy.to_byte()), Some(strategy));
+        }
+        assert_eq!(IndexStrategy::FastRange.index(u64::MAX, 1000), 999);
+        assert_eq!(IndexStrategy::Modulo.probe(10, 3, 2), 16);
+        assert_eq!(IndexStrategy::EnhancedDoubleHash.probe(10, 3, 2), 24);
+    }
+}
@@ -0,0 +1,23 @@
+//! Toy Bloom Filter implementation in Rust
+
+mod bloom_filter;
+mod bloom_hash;
+mod bloom_set;
+mod builder;
+mod cached;
+mod dyn_bloom;
+mod error;
+mod frozen;
+#[cfg(feature = "hll")]
+mod hyperloglog;
+mod index_strategy;
+
+pub use bloom_filter::{BloomFilter, DeletableBloomFilter, FilterCascade, FilterConfig, FilterSnapshot, MergeReport};
+pub use bloom_hash::BloomHash;
+pub use bloom_set::{BloomSet, InsertOutcome};
+pub use builder::BloomFilterBuilder;
+pub use cached::CachedBloomFilter;
+pub use dyn_bloom::DynBloom;
+pub use error::BloomFilterError;
+pub use frozen::FrozenBloomFilter;
+pub use index_strategy::IndexStrategy;